@@ -1,71 +1,304 @@
-use crate::config::group_context::CipherSuiteFFI;
-use crate::config::SignatureKeypairFFI;
+use crate::arc_unwrap_or_clone;
+use crate::config::crypto_provider::{
+    CryptoProviderProtocol, CryptoProviderWrapper, SignerProtocol, SignerResolver,
+    SigningKeyResolverProtocol,
+};
+use crate::config::group_context::{CipherSuiteFFI, ExtensionListFFI};
+use crate::config::group_state::{
+    ExternalPskIdFFI, GroupSnapshot, GroupStateStorageAdapter, GroupStateStorageProtocol,
+};
+use crate::config::metrics::Metrics;
+use crate::config::mls_rules::UniFFIMlsRules;
 use crate::config::SigningIdentityFFI;
-use crate::config::{ClientConfigFFI, UniFFIConfig};
-use crate::group::{GroupFFI, JoinInfo};
-use crate::message::MessageFFI;
+use crate::config::{with_group_state_storage, ClientConfigFFI, ClientGroupStorage, UniFFIConfig};
+use crate::config::{
+    SecretBytes, SignatureKeypairFFI, SignaturePublicKeyFFI, SignatureSecretKeyFFI,
+};
+use crate::crypto::{cipher_suite_provider, default_crypto_provider};
+use crate::group::{ExternalCommitOutputFFI, GroupFFI, JoinInfo};
+use crate::message::{GroupInfoFFI, KeyPackageFFI, MessageFFI};
 use crate::MlSrsError;
 
-use std::sync::Arc;
-use std::sync::Mutex;
+use std::ops::Deref;
+#[cfg(not(mls_build_async))]
+use std::sync::RwLock;
+use std::sync::{Arc, Mutex, Weak};
+#[cfg(mls_build_async)]
+use tokio::sync::RwLock;
 
+use mls_rs::crypto::SignatureSecretKey;
 use mls_rs::error::{IntoAnyError, MlsError};
+use mls_rs::mls_rs_codec::MlsDecode;
 use mls_rs::mls_rules::{CommitOptions, DefaultMlsRules, EncryptionOptions};
-use mls_rs::{CipherSuiteProvider, CryptoProvider};
+use mls_rs::storage_provider::in_memory::InMemoryGroupStateStorage;
+use mls_rs::time::MlsTime;
+use mls_rs::CipherSuiteProvider;
+use mls_rs_core::extension::ExtensionType;
+use mls_rs_core::group::ProposalType;
 use mls_rs_core::identity::{BasicCredential, SigningIdentity};
-use mls_rs_crypto_cryptokit::CryptoKitProvider;
 
 /// An MLS client used to create key packages and manage groups.
 ///
 /// See [`mls_rs::Client`] for details.
 #[derive(Clone, Debug, uniffi::Object)]
 pub struct ClientFFI {
-    inner: mls_rs::client::Client<UniFFIConfig>,
+    /// The client, `None` once it was wiped.
+    inner: Arc<RwLock<Option<mls_rs::client::Client<UniFFIConfig>>>>,
+    config: ClientConfigFFI,
+    mls_rules: UniFFIMlsRules,
+    /// States of the groups created, joined or loaded by this client,
+    /// cleared by [`ClientFFI::wipe`].
+    groups: Arc<Mutex<Vec<Weak<RwLock<Option<mls_rs::Group<UniFFIConfig>>>>>>>,
 }
 
-#[maybe_async::must_be_sync]
-#[uniffi::export]
+/// A lock on a client which was not wiped, see [`ClientFFI::wipe`].
+struct ClientGuard<G>(G);
+
+impl<G: Deref<Target = Option<mls_rs::Client<UniFFIConfig>>>> ClientGuard<G> {
+    fn new(guard: G) -> Result<Self, MlSrsError> {
+        match guard.is_some() {
+            true => Ok(Self(guard)),
+            false => Err(MlSrsError::Wiped),
+        }
+    }
+}
+
+impl<G: Deref<Target = Option<mls_rs::Client<UniFFIConfig>>>> Deref for ClientGuard<G> {
+    type Target = mls_rs::Client<UniFFIConfig>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref().expect("checked in ClientGuard::new")
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 impl ClientFFI {
-    /// Create a new client.
-    ///
-    /// The user is identified by `id`, which will be used to create a
-    /// basic credential together with the signature keypair.
-    ///
-    /// See [`mls_rs::Client::builder`] for details.
-    #[uniffi::constructor]
-    pub fn new(
+    fn mls_rules(client_config: &ClientConfigFFI) -> UniFFIMlsRules {
+        let commit_options = CommitOptions::default()
+            .with_ratchet_tree_extension(client_config.use_ratchet_tree_extension)
+            .with_single_welcome_message(client_config.single_welcome_message)
+            .with_path_required(client_config.path_required)
+            .with_allow_external_commit(client_config.allow_external_commit);
+        let encryption_options = EncryptionOptions::new(
+            client_config.encrypt_control_messages,
+            mls_rs::client_builder::PaddingMode::StepFunction,
+        );
+        UniFFIMlsRules::new(
+            DefaultMlsRules::new()
+                .with_commit_options(commit_options)
+                .with_encryption_options(encryption_options),
+        )
+        .with_max_group_size(client_config.max_group_size)
+        .with_max_authenticated_data_size(client_config.max_authenticated_data_size)
+        .with_max_past_epochs(client_config.max_past_epochs)
+        .with_foreign_rules(client_config.mls_rules.clone())
+        .with_proposal_validator(client_config.proposal_validator.clone())
+        .with_custom_proposal_policies(
+            client_config.custom_proposal_policies.clone(),
+            client_config.reject_unknown_custom_proposals,
+        )
+    }
+
+    fn build(
         id: Vec<u8>,
         signature_keypair: SignatureKeypairFFI,
+        key_resolver: Option<Arc<dyn SigningKeyResolverProtocol>>,
         client_config: ClientConfigFFI,
+        mls_rules: UniFFIMlsRules,
     ) -> Self {
+        let client_config = client_config.with_namespaced_storage();
         let cipher_suite = signature_keypair.cipher_suite;
         let public_key = signature_keypair.public_key;
         let secret_key = signature_keypair.secret_key;
-        let crypto_provider = CryptoKitProvider::default();
         let basic_credential = BasicCredential::new(id);
         let signing_identity =
             SigningIdentity::new(basic_credential.into_credential(), public_key.into());
-        let commit_options = CommitOptions::default()
-            .with_ratchet_tree_extension(client_config.use_ratchet_tree_extension)
-            .with_single_welcome_message(true);
-        let encryption_options = EncryptionOptions::new(
-            true, //encrypt control messages
-            mls_rs::client_builder::PaddingMode::StepFunction,
-        );
-        let mls_rules = DefaultMlsRules::new()
-            .with_commit_options(commit_options)
-            .with_encryption_options(encryption_options);
         let client = mls_rs::Client::builder()
-            .crypto_provider(crypto_provider)
-            .psk_store(client_config.pre_shared_key_storage.into())
-            .identity_provider(client_config.identity_provider_storage.into())
+            .crypto_provider(
+                CryptoProviderWrapper::from(client_config.crypto_provider.clone())
+                    .with_key_resolver(key_resolver)
+                    .with_metrics(Metrics::new(client_config.metrics.clone())),
+            )
+            .psk_store(client_config.pre_shared_key_storage.clone().into())
+            .identity_provider(client_config.identity_provider_storage.clone().into())
             .signing_identity(signing_identity, secret_key.into(), cipher_suite.into())
-            .key_package_repo(client_config.client_keypackage_storage.into())
-            .group_state_storage(client_config.group_state_storage.into())
-            .mls_rules(mls_rules)
+            .key_package_repo(client_config.client_keypackage_storage.clone().into())
+            .group_state_storage(ClientGroupStorage::new(&client_config))
+            .mls_rules(mls_rules.clone())
+            .used_protocol_version(client_config.protocol_version.into())
+            .extension_types(
+                client_config
+                    .extension_types
+                    .iter()
+                    .map(|t| ExtensionType::new(*t)),
+            )
+            .custom_proposal_types(
+                client_config
+                    .custom_proposal_types
+                    .iter()
+                    .map(|t| ProposalType::new(*t)),
+            )
             .build();
 
-        ClientFFI { inner: client }
+        ClientFFI {
+            inner: Arc::new(RwLock::new(Some(client))),
+            config: client_config,
+            mls_rules,
+            groups: Default::default(),
+        }
+    }
+
+    /// Lock the client for a single operation.
+    ///
+    /// Operations share the lock, [`Self::wipe`] waits for them to
+    /// finish.
+    #[cfg(not(mls_build_async))]
+    fn client(
+        &self,
+    ) -> Result<
+        ClientGuard<std::sync::RwLockReadGuard<'_, Option<mls_rs::Client<UniFFIConfig>>>>,
+        MlSrsError,
+    > {
+        ClientGuard::new(self.inner.read().unwrap())
+    }
+
+    #[cfg(mls_build_async)]
+    async fn client(
+        &self,
+    ) -> Result<
+        ClientGuard<tokio::sync::RwLockReadGuard<'_, Option<mls_rs::Client<UniFFIConfig>>>>,
+        MlSrsError,
+    > {
+        ClientGuard::new(self.inner.read().await)
+    }
+
+    /// Join with an external commit, shared by
+    /// [`ClientFFI::commit_external`] and
+    /// [`ClientFFI::resync_external`].
+    async fn external_commit(
+        &self,
+        group_info: Arc<MessageFFI>,
+        removal: Option<u32>,
+        external_psk_ids: Vec<Arc<ExternalPskIdFFI>>,
+        authenticated_data: Vec<u8>,
+    ) -> Result<ExternalCommitOutputFFI, MlSrsError> {
+        self.mls_rules
+            .check_authenticated_data(&authenticated_data)?;
+        let client = self.client().await?;
+        let mut builder = client
+            .external_commit_builder()?
+            .with_authenticated_data(authenticated_data);
+        if let Some(removal) = removal {
+            builder = builder.with_removal(removal);
+        }
+        for external_psk_id in external_psk_ids {
+            builder = builder.with_external_psk(external_psk_id.inner());
+        }
+        let (group, commit_message) = builder.build(arc_unwrap_or_clone(group_info).inner).await?;
+        Ok(ExternalCommitOutputFFI {
+            group: Arc::new(self.group(group)),
+            commit_message: Arc::new(commit_message.into()),
+        })
+    }
+
+    fn group(&self, group: mls_rs::Group<UniFFIConfig>) -> GroupFFI {
+        let inner = Arc::new(RwLock::new(Some(group)));
+        let mut groups = self.groups.lock().unwrap();
+        groups.retain(|group| group.strong_count() > 0);
+        groups.push(Arc::downgrade(&inner));
+        GroupFFI {
+            inner,
+            mls_rules: self.mls_rules.clone(),
+            metrics: Metrics::new(self.config.metrics.clone()),
+            identity_provider: self.config.identity_provider_storage.clone(),
+            crypto_provider: self.config.crypto_provider.clone(),
+            pending_welcomes: Default::default(),
+            cached_proposals: Default::default(),
+            roster_cache: Default::default(),
+        }
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+impl ClientFFI {
+    /// Create a new client.
+    ///
+    /// The user is identified by `id`, which will be used to create a
+    /// basic credential together with the signature keypair.
+    ///
+    /// See [`mls_rs::Client::builder`] for details.
+    #[uniffi::constructor]
+    pub fn new(
+        id: Vec<u8>,
+        signature_keypair: SignatureKeypairFFI,
+        client_config: ClientConfigFFI,
+    ) -> Self {
+        let key_resolver = client_config.signing_key_resolver.clone();
+        let mls_rules = Self::mls_rules(&client_config);
+        Self::build(
+            id,
+            signature_keypair,
+            key_resolver,
+            client_config,
+            mls_rules,
+        )
+    }
+
+    /// Create a new client which delegates signing to `signer`.
+    ///
+    /// This works like [`ClientFFI::new`], but the signature secret
+    /// key never enters this library: leaf nodes, key packages and
+    /// commits are signed by `signer`, e.g., backed by a hardware
+    /// keystore.
+    #[uniffi::constructor]
+    pub async fn new_with_signer(
+        id: Vec<u8>,
+        cipher_suite: CipherSuiteFFI,
+        signer: Arc<dyn SignerProtocol>,
+        client_config: ClientConfigFFI,
+    ) -> Result<Self, MlSrsError> {
+        let signature_keypair = SignatureKeypairFFI {
+            cipher_suite,
+            public_key: signer.public_key().await?,
+            // Never used, all signatures are created by `signer`.
+            secret_key: SignatureSecretKeyFFI {
+                bytes: SecretBytes::default(),
+            },
+        };
+        let key_resolver: Arc<dyn SigningKeyResolverProtocol> =
+            Arc::new(SignerResolver::from(signer));
+        let mls_rules = Self::mls_rules(&client_config);
+        Ok(Self::build(
+            id,
+            signature_keypair,
+            Some(key_resolver),
+            client_config,
+            mls_rules,
+        ))
+    }
+
+    /// Create a client with a new signing identity, sharing the
+    /// configuration and storage of this client.
+    ///
+    /// Use this after rotating credentials: key packages generated by
+    /// the new client carry the new identity, while existing groups
+    /// keep working with the old one until each of them is committed
+    /// with [`GroupFFI::commit_new_identity`].
+    pub fn with_new_signing_identity(
+        &self,
+        signature_keypair: SignatureKeypairFFI,
+        id: Vec<u8>,
+    ) -> ClientFFI {
+        Self::build(
+            id,
+            signature_keypair,
+            self.config.signing_key_resolver.clone(),
+            self.config.clone(),
+            self.mls_rules.clone(),
+        )
     }
 
     /// Generate a new key package for this client.
@@ -74,21 +307,34 @@ impl ClientFFI {
     /// needed when joining a group and can be published to a server
     /// so other clients can look it up.
     ///
+    /// The optional extension lists are embedded in the key package
+    /// and in its leaf node, respectively.
+    ///
     /// See [`mls_rs::Client::generate_key_package_message`] for
     /// details.
-    pub async fn generate_key_package_message(&self) -> Result<MessageFFI, MlSrsError> {
+    pub async fn generate_key_package_message(
+        &self,
+        key_package_extensions: Option<Arc<ExtensionListFFI>>,
+        leaf_node_extensions: Option<Arc<ExtensionListFFI>>,
+    ) -> Result<MessageFFI, MlSrsError> {
         let message = self
-            .inner
+            .client()
+            .await?
             .generate_key_package_message(
-                mls_rs::ExtensionList::default(),
-                mls_rs::ExtensionList::default(),
+                key_package_extensions
+                    .map(|e| e.as_ref().into())
+                    .unwrap_or_default(),
+                leaf_node_extensions
+                    .map(|e| e.as_ref().into())
+                    .unwrap_or_default(),
             )
             .await?;
         Ok(message.into())
     }
 
-    pub fn signing_identity(&self) -> Result<Arc<SigningIdentityFFI>, MlSrsError> {
-        let (signing_identity, _) = self.inner.signing_identity()?;
+    pub async fn signing_identity(&self) -> Result<Arc<SigningIdentityFFI>, MlSrsError> {
+        let client = self.client().await?;
+        let (signing_identity, _) = client.signing_identity()?;
         Ok(Arc::new(signing_identity.clone().into()))
     }
 
@@ -100,25 +346,37 @@ impl ClientFFI {
     /// See [`mls_rs::Client::create_group`] and
     /// [`mls_rs::Client::create_group_with_id`] for details.
     pub async fn create_group(&self, group_id: Option<Vec<u8>>) -> Result<GroupFFI, MlSrsError> {
+        self.create_group_with_extensions(group_id, Arc::new(ExtensionListFFI::new(Vec::new())))
+            .await
+    }
+
+    /// Create a new group with the given group context extensions,
+    /// e.g., a
+    /// [`required_capabilities_extension`](crate::config::group_context::required_capabilities_extension).
+    pub async fn create_group_with_extensions(
+        &self,
+        group_id: Option<Vec<u8>>,
+        group_context_extensions: Arc<ExtensionListFFI>,
+    ) -> Result<GroupFFI, MlSrsError> {
+        let group_context_extensions = group_context_extensions.as_ref().into();
+        let client = self.client().await?;
         let inner = match group_id {
             Some(group_id) => {
-                self.inner
+                client
                     .create_group_with_id(
                         group_id,
-                        mls_rs::ExtensionList::new(),
+                        group_context_extensions,
                         mls_rs::ExtensionList::new(),
                     )
                     .await?
             }
             None => {
-                self.inner
-                    .create_group(mls_rs::ExtensionList::new(), mls_rs::ExtensionList::new())
+                client
+                    .create_group(group_context_extensions, mls_rs::ExtensionList::new())
                     .await?
             }
         };
-        Ok(GroupFFI {
-            inner: Arc::new(Mutex::new(inner)),
-        })
+        Ok(self.group(inner))
     }
 
     /// Join an existing group.
@@ -129,17 +387,20 @@ impl ClientFFI {
     /// This variant doesn't support an imported ratched tree
     ///
     /// See [`mls_rs::Client::join_group`] for details.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub async fn join_group(
         &self,
         // ratchet_tree: Option<RatchetTree>,
         welcome_message: &MessageFFI,
     ) -> Result<JoinInfo, MlSrsError> {
         // let ratchet_tree = ratchet_tree.map(TryInto::try_into).transpose()?;
-        let (group, new_member_info) = self.inner.join_group(None, &welcome_message.inner).await?;
+        let (group, new_member_info) = self
+            .client()
+            .await?
+            .join_group(None, &welcome_message.inner)
+            .await?;
 
-        let group = Arc::new(GroupFFI {
-            inner: Arc::new(Mutex::new(group)),
-        });
+        let group = Arc::new(self.group(group));
         let group_info_extensions = Arc::new(new_member_info.group_info_extensions.into());
         Ok(JoinInfo {
             group,
@@ -147,18 +408,248 @@ impl ClientFFI {
         })
     }
 
+    /// Join a group with an external commit.
+    ///
+    /// `group_info` must include the external public key, see
+    /// [`GroupFFI::group_info_message`]. The commit proves possession
+    /// of the pre-shared keys `external_psk_ids`, which must be found
+    /// in the
+    /// [`ClientConfigFFI::pre_shared_key_storage`](crate::config::ClientConfigFFI::pre_shared_key_storage)
+    /// of this client and of the group members.
+    ///
+    /// See [`mls_rs::Client::external_commit_builder`] for details.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn commit_external(
+        &self,
+        group_info: Arc<MessageFFI>,
+        external_psk_ids: Vec<Arc<ExternalPskIdFFI>>,
+        authenticated_data: Vec<u8>,
+    ) -> Result<ExternalCommitOutputFFI, MlSrsError> {
+        self.external_commit(group_info, None, external_psk_ids, authenticated_data)
+            .await
+    }
+
+    /// Rejoin a group with an external commit after losing the group
+    /// state, removing our previous leaf at `prior_leaf_index`.
+    ///
+    /// The members accept the removal only if the
+    /// [`IdentityProviderProtocol::valid_successor`](crate::config::IdentityProviderProtocol::valid_successor)
+    /// check of the new identity against the removed one succeeds.
+    /// Otherwise this works like [`ClientFFI::commit_external`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn resync_external(
+        &self,
+        group_info: Arc<MessageFFI>,
+        prior_leaf_index: u32,
+        external_psk_ids: Vec<Arc<ExternalPskIdFFI>>,
+        authenticated_data: Vec<u8>,
+    ) -> Result<ExternalCommitOutputFFI, MlSrsError> {
+        self.external_commit(
+            group_info,
+            Some(prior_leaf_index),
+            external_psk_ids,
+            authenticated_data,
+        )
+        .await
+    }
+
+    /// Check if this client holds a key package targeted by
+    /// `welcome_message`.
+    ///
+    /// Nothing is consumed, so the welcome can still be passed to
+    /// [`ClientFFI::join_group`] afterwards.
+    pub async fn can_join(&self, welcome_message: Arc<MessageFFI>) -> Result<bool, MlSrsError> {
+        for key_package_ref in welcome_message.inner.welcome_key_package_references() {
+            let key_package = self
+                .config
+                .client_keypackage_storage
+                .get(key_package_ref.to_vec())
+                .await?;
+            if key_package.is_some() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Generate key packages according to
+    /// [`ClientConfigFFI::key_package_policy`].
+    ///
+    /// If fewer than `threshold` unexpired key packages remain in
+    /// [`ClientConfigFFI::client_keypackage_storage`], new ones are
+    /// generated until `target_count` is reached. The new key package
+    /// messages are returned for upload; nothing is generated without
+    /// a policy.
+    ///
+    /// Expired key packages are not deleted, see
+    /// [`Self::purge_expired_key_packages`].
+    pub async fn replenish_key_packages(&self) -> Result<Vec<MessageFFI>, MlSrsError> {
+        let Some(policy) = self.config.key_package_policy else {
+            return Ok(Vec::new());
+        };
+        let storage = &self.config.client_keypackage_storage;
+        let now = MlsTime::now().seconds_since_epoch();
+        let mut remaining = 0;
+        for id in storage.key_package_ids().await? {
+            if let Some(key_package) = storage.get(id).await? {
+                if key_package.expiration > now {
+                    remaining += 1;
+                }
+            }
+        }
+        if remaining >= policy.threshold {
+            return Ok(Vec::new());
+        }
+
+        let mut key_packages = Vec::new();
+        for _ in remaining..policy.target_count {
+            key_packages.push(self.generate_key_package_message(None, None).await?);
+        }
+        Ok(key_packages)
+    }
+
+    /// Delete all key packages which expired before `now`.
+    ///
+    /// `now` is given in seconds since the Unix epoch. Returns the
+    /// number of key packages deleted through
+    /// [`ClientConfigFFI::client_keypackage_storage`].
+    pub async fn purge_expired_key_packages(&self, now: u64) -> Result<u32, MlSrsError> {
+        let storage = &self.config.client_keypackage_storage;
+        let mut purged = 0;
+        for id in storage.key_package_ids().await? {
+            let Some(key_package) = storage.get(id.clone()).await? else {
+                continue;
+            };
+            if key_package.expiration < now {
+                storage.delete(id).await?;
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+
+    /// Delete all key packages and group states of this client.
+    ///
+    /// This is meant for account deletion and device handoff. The
+    /// in-memory state of the client and of its groups is dropped
+    /// first, which zeroizes the signature secret key and the epoch
+    /// secrets. Afterwards every call on the client or its groups
+    /// fails with [`MlSrsError::Wiped`], except for `wipe` itself,
+    /// which can be retried if deleting from storage failed.
+    pub async fn wipe(&self) -> Result<(), MlSrsError> {
+        #[cfg(not(mls_build_async))]
+        self.inner.write().unwrap().take();
+        #[cfg(mls_build_async)]
+        self.inner.write().await.take();
+        let groups = std::mem::take(&mut *self.groups.lock().unwrap());
+        for group in groups.iter().filter_map(Weak::upgrade) {
+            #[cfg(not(mls_build_async))]
+            group.write().unwrap().take();
+            #[cfg(mls_build_async)]
+            group.write().await.take();
+        }
+
+        let key_package_storage = &self.config.client_keypackage_storage;
+        for id in key_package_storage.key_package_ids().await? {
+            key_package_storage.delete(id).await?;
+        }
+
+        let group_state_storage = &self.config.group_state_storage;
+        for group_id in group_state_storage.group_ids().await? {
+            group_state_storage.delete_group(group_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Delete the group state and all epoch records of `group_id`
+    /// from [`ClientConfigFFI::group_state_storage`].
+    ///
+    /// Use this after leaving a group or when it was disbanded.
+    /// Loaded [`GroupFFI`] instances for the group should no longer
+    /// be used.
+    pub async fn delete_group(&self, group_id: Vec<u8>) -> Result<(), MlSrsError> {
+        self.config.group_state_storage.delete_group(group_id).await
+    }
+
     /// Load an existing group.
     ///
     /// See [`mls_rs::Client::load_group`] for details.
     pub async fn load_group(&self, group_id: Vec<u8>) -> Result<GroupFFI, MlSrsError> {
-        self.inner
+        self.client()
+            .await?
             .load_group(&group_id)
             .await
-            .map(|g| GroupFFI {
-                inner: Arc::new(Mutex::new(g)),
-            })
+            .map(|g| self.group(g))
             .map_err(Into::into)
     }
+
+    /// Restore a group serialized with [`GroupFFI::to_snapshot_bytes`].
+    ///
+    /// The group is loaded from the snapshot alone, this client's
+    /// [`ClientConfigFFI::group_state_storage`] is only used once the
+    /// group is written, e.g., with [`GroupFFI::write_to_storage`].
+    pub async fn group_from_snapshot(&self, bytes: Vec<u8>) -> Result<GroupFFI, MlSrsError> {
+        let snapshot = GroupSnapshot::mls_decode(&mut &*bytes)?;
+        let group_id = snapshot.group_id.clone();
+        let storage: Arc<dyn GroupStateStorageProtocol> = Arc::new(GroupStateStorageAdapter::new(
+            InMemoryGroupStateStorage::new(),
+        ));
+        snapshot.write(&*storage).await?;
+        let client = self.client().await?;
+        #[cfg(not(mls_build_async))]
+        let group = with_group_state_storage(storage, || client.load_group(&group_id))?;
+        #[cfg(mls_build_async)]
+        let group = with_group_state_storage(storage, client.load_group(&group_id)).await?;
+        Ok(self.group(group))
+    }
+
+    /// Classify an incoming message by its wire format.
+    ///
+    /// Welcome messages are used to join their group right away.
+    /// PublicMessage and PrivateMessage messages are returned with
+    /// their group id to be processed by that group, e.g., with
+    /// [`SessionFFI::handle_message`](crate::session::SessionFFI::handle_message).
+    pub async fn process_any_message(
+        &self,
+        message: Arc<MessageFFI>,
+    ) -> Result<ClientMessageFFI, MlSrsError> {
+        if message.is_welcome() {
+            let join_info = self.join_group(&message).await?;
+            return Ok(ClientMessageFFI::Welcome { join_info });
+        }
+        if let Some(key_package) = message.key_package()? {
+            return Ok(ClientMessageFFI::KeyPackage { key_package });
+        }
+        if let Some(group_info) = message.group_info()? {
+            return Ok(ClientMessageFFI::GroupInfo { group_info });
+        }
+        let group_id = message
+            .group_id()
+            .ok_or(MlSrsError::UnexpecteMessageFormat)?;
+        Ok(ClientMessageFFI::GroupMessage { group_id, message })
+    }
+}
+
+/// A message classified by [`ClientFFI::process_any_message`].
+#[derive(Clone, uniffi::Enum)]
+pub enum ClientMessageFFI {
+    /// A Welcome message which was used to join a group.
+    Welcome {
+        join_info: JoinInfo,
+    },
+    KeyPackage {
+        key_package: Arc<KeyPackageFFI>,
+    },
+    GroupInfo {
+        group_info: GroupInfoFFI,
+    },
+    /// A message for the group with id `group_id`, not yet
+    /// processed.
+    GroupMessage {
+        group_id: Vec<u8>,
+        message: Arc<MessageFFI>,
+    },
 }
 
 /// Generate a MLS signature keypair.
@@ -167,15 +658,13 @@ impl ClientFFI {
 ///
 /// See [`mls_rs::CipherSuiteProvider::signature_key_generate`]
 /// for details.
-#[maybe_async::must_be_sync]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 #[uniffi::export]
 pub async fn generate_signature_keypair(
     cipher_suite: CipherSuiteFFI,
 ) -> Result<SignatureKeypairFFI, MlSrsError> {
-    let crypto_provider = mls_rs_crypto_cryptokit::CryptoKitProvider::default();
-    let cipher_suite_provider = crypto_provider
-        .cipher_suite_provider(cipher_suite.into())
-        .ok_or(MlsError::UnsupportedCipherSuite(cipher_suite.into()))?;
+    let cipher_suite_provider = cipher_suite_provider(cipher_suite)?;
 
     let (secret_key, public_key) = cipher_suite_provider
         .signature_key_generate()
@@ -188,3 +677,203 @@ pub async fn generate_signature_keypair(
         secret_key: secret_key.into(),
     })
 }
+
+/// Minimum size of the seed of [`signature_keypair_from_seed`].
+const MIN_SEED_SIZE: u32 = 32;
+
+/// KDF label of [`signature_keypair_from_seed`]. Part of the stable
+/// derivation, don't change it.
+const SEED_SIGNATURE_KEY_LABEL: &[u8] = b"MLS signature key";
+
+/// Derive a MLS signature keypair from `seed`.
+///
+/// The same seed always results in the same keypair, so identity keys
+/// can be kept in an existing key hierarchy. Seeds shorter than 32
+/// bytes fail with [`MlSrsError::SeedTooShort`].
+///
+/// Only the Ed25519 cipher suites are supported: an Ed25519 secret
+/// key is any 32 byte string, which is expanded from the seed with
+/// the cipher suite's KDF. Other cipher suites fail with an
+/// unsupported cipher suite error.
+///
+/// The secret key is `KDF.Expand(KDF.Extract("", seed), "MLS signature
+/// key", 32)`. This is a stable format: changing the label or the
+/// derivation would change every key derived from an existing seed.
+///
+/// See [`mls_rs::CipherSuiteProvider::signature_key_derive_public`]
+/// for details.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+pub async fn signature_keypair_from_seed(
+    cipher_suite: CipherSuiteFFI,
+    seed: Vec<u8>,
+) -> Result<SignatureKeypairFFI, MlSrsError> {
+    let secret_key_size = match cipher_suite {
+        CipherSuiteFFI::Curve25519Aes128 | CipherSuiteFFI::Curve25519ChaCha => 32,
+        CipherSuiteFFI::P256Aes128 => {
+            return Err(MlsError::UnsupportedCipherSuite(cipher_suite.into()).into())
+        }
+    };
+    if seed.len() < MIN_SEED_SIZE as usize {
+        return Err(MlSrsError::SeedTooShort {
+            size: seed.len() as u64,
+            min_size: MIN_SEED_SIZE,
+        });
+    }
+    let cipher_suite_provider = cipher_suite_provider(cipher_suite)?;
+
+    let prk = cipher_suite_provider
+        .kdf_extract(&[], &seed)
+        .await
+        .map_err(|err| MlsError::CryptoProviderError(err.into_any_error()))?;
+    let secret_key = cipher_suite_provider
+        .kdf_expand(&prk, SEED_SIGNATURE_KEY_LABEL, secret_key_size)
+        .await
+        .map_err(|err| MlsError::CryptoProviderError(err.into_any_error()))?;
+    let secret_key = SignatureSecretKey::new(secret_key.to_vec());
+
+    let public_key = cipher_suite_provider
+        .signature_key_derive_public(&secret_key)
+        .await
+        .map_err(|err| MlsError::CryptoProviderError(err.into_any_error()))?;
+
+    Ok(SignatureKeypairFFI {
+        cipher_suite,
+        public_key: public_key.into(),
+        secret_key: secret_key.into(),
+    })
+}
+
+/// Reconstruct the signature keypair belonging to `secret_key`.
+///
+/// See [`mls_rs::CipherSuiteProvider::signature_key_derive_public`]
+/// for details.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+pub async fn signature_public_key_for(
+    secret_key: SignatureSecretKeyFFI,
+    cipher_suite: CipherSuiteFFI,
+) -> Result<SignatureKeypairFFI, MlSrsError> {
+    let cipher_suite_provider = cipher_suite_provider(cipher_suite)?;
+    let secret_key = secret_key.into();
+
+    let public_key = cipher_suite_provider
+        .signature_key_derive_public(&secret_key)
+        .await
+        .map_err(|err| MlsError::CryptoProviderError(err.into_any_error()))?;
+
+    Ok(SignatureKeypairFFI {
+        cipher_suite,
+        public_key: public_key.into(),
+        secret_key: secret_key.into(),
+    })
+}
+
+/// Sign `data` with a MLS signature secret key.
+///
+/// No MLS label is applied, so the signature can be checked with any
+/// implementation of the cipher suite's signature scheme.
+///
+/// See [`mls_rs::CipherSuiteProvider::sign`] for details.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+pub async fn sign(
+    cipher_suite: CipherSuiteFFI,
+    secret_key: SignatureSecretKeyFFI,
+    data: Vec<u8>,
+) -> Result<Vec<u8>, MlSrsError> {
+    let cipher_suite_provider = cipher_suite_provider(cipher_suite)?;
+
+    cipher_suite_provider
+        .sign(&secret_key.into(), &data)
+        .await
+        .map_err(|err| MlsError::CryptoProviderError(err.into_any_error()).into())
+}
+
+/// Check a signature created with [`sign`].
+///
+/// Returns `false` if `signature` is not a valid signature of `data`
+/// under `public_key`, including signatures which can't be decoded.
+/// Fails with [`MlSrsError::MalformedPublicKey`] if `public_key` does
+/// not have the size of a key of `cipher_suite`.
+///
+/// See [`mls_rs::CipherSuiteProvider::verify`] for details.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+pub async fn verify(
+    cipher_suite: CipherSuiteFFI,
+    public_key: SignaturePublicKeyFFI,
+    signature: Vec<u8>,
+    data: Vec<u8>,
+) -> Result<bool, MlSrsError> {
+    let cipher_suite_provider = cipher_suite_provider(cipher_suite)?;
+    check_public_key_size(cipher_suite, &cipher_suite_provider, &public_key.bytes).await?;
+
+    // With a public key of the right size, verification fails because
+    // of the signature, or because the key doesn't decode. No
+    // signature is valid in either case.
+    Ok(cipher_suite_provider
+        .verify(&public_key.into(), &signature, &data)
+        .await
+        .is_ok())
+}
+
+/// Check that `public_key` has the size of the signature keys of
+/// `cipher_suite_provider`.
+///
+/// The provider has no accessor for the size, so it is taken from a
+/// key generated once per cipher suite.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+async fn check_public_key_size(
+    cipher_suite: CipherSuiteFFI,
+    cipher_suite_provider: &impl CipherSuiteProvider,
+    public_key: &[u8],
+) -> Result<(), MlSrsError> {
+    static PUBLIC_KEY_SIZES: Mutex<Vec<(u16, usize)>> = Mutex::new(Vec::new());
+
+    let cipher_suite = mls_rs::CipherSuite::from(cipher_suite).raw_value();
+    let cached_size = PUBLIC_KEY_SIZES
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(suite, _)| *suite == cipher_suite)
+        .map(|(_, size)| *size);
+    let size = match cached_size {
+        Some(size) => size,
+        None => {
+            let (_, public_key) = cipher_suite_provider
+                .signature_key_generate()
+                .await
+                .map_err(|err| MlsError::CryptoProviderError(err.into_any_error()))?;
+            let size = public_key.as_bytes().len();
+            PUBLIC_KEY_SIZES.lock().unwrap().push((cipher_suite, size));
+            size
+        }
+    };
+    match public_key.len() == size {
+        true => Ok(()),
+        false => Err(MlSrsError::MalformedPublicKey),
+    }
+}
+
+/// List the cipher suites supported by `crypto_provider`, or by the
+/// built-in provider if it is `None`.
+///
+/// Suites which can't be represented by [`CipherSuiteFFI`] are
+/// omitted.
+///
+/// See [`mls_rs::CryptoProvider::supported_cipher_suites`] for
+/// details.
+#[uniffi::export(default(crypto_provider = None))]
+pub fn supported_cipher_suites(
+    crypto_provider: Option<Arc<dyn CryptoProviderProtocol>>,
+) -> Vec<CipherSuiteFFI> {
+    crypto_provider
+        .unwrap_or_else(default_crypto_provider)
+        .supported_cipher_suites()
+}
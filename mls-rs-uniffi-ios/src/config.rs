@@ -1,11 +1,17 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
 use mls_rs::mls_rs_codec::MlsEncode;
 use mls_rs::psk::{ExternalPskId, PreSharedKey};
 use mls_rs_core::identity;
 use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::ops::Deref;
 use std::sync::Arc;
+use zeroize::{ZeroizeOnDrop, Zeroizing};
 
 use mls_rs::{
-    client_builder::{self, WithGroupStateStorage, WithKeyPackageRepo, WithPskStore},
+    client_builder::{self, WithGroupStateStorage, WithKeyPackageRepo, WithMlsRules, WithPskStore},
     storage_provider::in_memory::{
         InMemoryGroupStateStorage, InMemoryKeyPackageStorage, InMemoryPreSharedKeyStorage,
     },
@@ -14,20 +20,37 @@ use mls_rs::{
 
 use mls_rs_core::key_package::KeyPackageData;
 
-use mls_rs_crypto_cryptokit::CryptoKitProvider;
-
+use self::crypto_provider::{
+    CryptoProviderProtocol, CryptoProviderWrapper, SigningKeyResolverProtocol,
+};
 use self::group_context::{CipherSuiteFFI, ExtensionListFFI};
 use self::group_state::{
     GroupStateStorageAdapter, GroupStateStorageProtocol, KeyPackageStorageAdapter,
     KeyPackageStorageProtocol, PreSharedKeyStorageAdapter, PreSharedKeyStorageProtocol,
 };
+use self::metrics::{MetricFFI, Metrics, MetricsProtocol};
+use self::mls_rules::{
+    CustomProposalPolicyFFI, MlsRulesProtocol, ProposalValidatorProtocol, UniFFIMlsRules,
+};
+use self::namespaced_storage::NamespacedStorage;
 use crate::config::member_validation_context::MemberValidationContextFFI;
 
+use crate::crypto::default_crypto_provider;
 use crate::mls_rs_error::MlSrsError;
+use crate::ProtocolVersion;
 
+pub mod crypto_provider;
+pub mod encrypted_storage;
+pub mod file_storage;
 pub mod group_context;
 pub mod group_state;
 pub mod member_validation_context;
+pub mod metrics;
+pub mod mls_rules;
+pub mod namespaced_storage;
+pub mod observed_storage;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
 #[derive(Debug, Clone)]
 pub struct PreSharedKeyStorageWrapper(Arc<dyn PreSharedKeyStorageProtocol>);
@@ -38,11 +61,13 @@ impl From<Arc<dyn PreSharedKeyStorageProtocol>> for PreSharedKeyStorageWrapper {
     }
 }
 
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 impl mls_rs::PreSharedKeyStorage for PreSharedKeyStorageWrapper {
     type Error = MlSrsError;
 
-    fn get(&self, psk_id: &ExternalPskId) -> Result<Option<PreSharedKey>, MlSrsError> {
-        let result = self.0.get(psk_id.mls_encode_to_vec()?);
+    async fn get(&self, psk_id: &ExternalPskId) -> Result<Option<PreSharedKey>, MlSrsError> {
+        let result = self.0.get(psk_id.mls_encode_to_vec()?).await;
         match result {
             Err(error) => Err(error),
             Ok(option) => match option {
@@ -62,12 +87,13 @@ impl From<Arc<dyn KeyPackageStorageProtocol>> for ClientKeyPackageStorage {
     }
 }
 
-#[maybe_async::must_be_sync]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 impl mls_rs_core::key_package::KeyPackageStorage for ClientKeyPackageStorage {
     type Error = MlSrsError;
 
     async fn delete(&mut self, id: &[u8]) -> Result<(), Self::Error> {
-        self.0.delete(id.to_vec().await)
+        self.0.delete(id.to_vec()).await
     }
 
     /// Store [`KeyPackageData`] that can be accessed by `id` in the future.
@@ -88,29 +114,149 @@ impl mls_rs_core::key_package::KeyPackageStorage for ClientKeyPackageStorage {
     async fn get(&self, id: &[u8]) -> Result<Option<KeyPackageData>, Self::Error> {
         self.0
             .get(id.to_vec())
+            .await
             .map(|result| result.map(|option| option.into()))
     }
 }
 
+/// Prefix of compressed group states.
+///
+/// Uncompressed group states start with the snapshot version, so
+/// they never begin with this marker.
+const COMPRESSED_STATE_MARKER: &[u8] = b"MLSZ";
+
+#[cfg(not(mls_build_async))]
+thread_local! {
+    /// Storage used by [`with_group_state_storage`].
+    static GROUP_STATE_STORAGE_OVERRIDE: std::cell::RefCell<Option<Arc<dyn GroupStateStorageProtocol>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+#[cfg(mls_build_async)]
+tokio::task_local! {
+    /// Storage used by [`with_group_state_storage`].
+    static GROUP_STATE_STORAGE_OVERRIDE: Option<Arc<dyn GroupStateStorageProtocol>>;
+}
+
+/// Call `call` with groups reading and writing `storage` instead of
+/// the storage of their client.
+///
+/// mls-rs only reads and writes group states through the storage of
+/// the client, so snapshots swap the storage for the call.
+#[cfg(not(mls_build_async))]
+pub(crate) fn with_group_state_storage<T>(
+    storage: Arc<dyn GroupStateStorageProtocol>,
+    call: impl FnOnce() -> T,
+) -> T {
+    struct Restore(Option<Arc<dyn GroupStateStorageProtocol>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            GROUP_STATE_STORAGE_OVERRIDE.with(|cell| cell.replace(self.0.take()));
+        }
+    }
+
+    let _restore = Restore(GROUP_STATE_STORAGE_OVERRIDE.with(|cell| cell.replace(Some(storage))));
+    call()
+}
+
+/// Await `call` with groups reading and writing `storage` instead of
+/// the storage of their client.
+///
+/// mls-rs only reads and writes group states through the storage of
+/// the client, so snapshots swap the storage for the call.
+#[cfg(mls_build_async)]
+pub(crate) async fn with_group_state_storage<F: std::future::Future>(
+    storage: Arc<dyn GroupStateStorageProtocol>,
+    call: F,
+) -> F::Output {
+    GROUP_STATE_STORAGE_OVERRIDE
+        .scope(Some(storage), call)
+        .await
+}
+
+/// The storage set by the enclosing [`with_group_state_storage`], if
+/// any.
+#[cfg(not(mls_build_async))]
+fn group_state_storage_override() -> Option<Arc<dyn GroupStateStorageProtocol>> {
+    GROUP_STATE_STORAGE_OVERRIDE.with(|cell| cell.borrow().clone())
+}
+
+/// The storage set by the enclosing [`with_group_state_storage`], if
+/// any.
+#[cfg(mls_build_async)]
+fn group_state_storage_override() -> Option<Arc<dyn GroupStateStorageProtocol>> {
+    GROUP_STATE_STORAGE_OVERRIDE
+        .try_with(|storage| storage.clone())
+        .ok()
+        .flatten()
+}
+
 #[derive(Debug, Clone)]
-pub struct ClientGroupStorage(Arc<dyn GroupStateStorageProtocol>);
+pub struct ClientGroupStorage {
+    inner: Arc<dyn GroupStateStorageProtocol>,
+    max_stored_epochs: Option<u32>,
+    compress_group_state: bool,
+    metrics: Metrics,
+}
 
-impl From<Arc<dyn GroupStateStorageProtocol>> for ClientGroupStorage {
-    fn from(value: Arc<dyn GroupStateStorageProtocol>) -> Self {
-        Self(value)
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl ClientGroupStorage {
+    pub fn new(client_config: &ClientConfigFFI) -> Self {
+        Self {
+            inner: client_config.group_state_storage.clone(),
+            max_stored_epochs: client_config.max_stored_epochs.map(|max_stored_epochs| {
+                match client_config.max_past_epochs {
+                    Some(max_past_epochs) => {
+                        max_stored_epochs.max(max_past_epochs.saturating_add(1))
+                    }
+                    None => max_stored_epochs,
+                }
+            }),
+            compress_group_state: client_config.compress_group_state,
+            metrics: Metrics::new(client_config.metrics.clone()),
+        }
+    }
+
+    /// The client's storage, unless overridden with
+    /// [`with_group_state_storage`].
+    fn storage(&self) -> Arc<dyn GroupStateStorageProtocol> {
+        group_state_storage_override().unwrap_or_else(|| self.inner.clone())
+    }
+
+    fn compress(data: &[u8]) -> Result<Vec<u8>, MlSrsError> {
+        let mut encoder =
+            DeflateEncoder::new(COMPRESSED_STATE_MARKER.to_vec(), Compression::default());
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn decompress(data: Vec<u8>) -> Result<Vec<u8>, MlSrsError> {
+        let Some(compressed) = data.strip_prefix(COMPRESSED_STATE_MARKER) else {
+            return Ok(data);
+        };
+        let mut decompressed = Vec::new();
+        DeflateDecoder::new(compressed).read_to_end(&mut decompressed)?;
+        Ok(decompressed)
     }
 }
 
-#[maybe_async::must_be_sync]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 impl mls_rs_core::group::GroupStateStorage for ClientGroupStorage {
     type Error = MlSrsError;
 
     async fn state(&self, group_id: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
-        self.0.state(group_id.to_vec()).await
+        self.storage()
+            .state(group_id.to_vec())
+            .await?
+            .map(Self::decompress)
+            .transpose()
     }
 
     async fn epoch(&self, group_id: &[u8], epoch_id: u64) -> Result<Option<Vec<u8>>, Self::Error> {
-        self.0.epoch(group_id.to_vec(), epoch_id).await
+        self.storage().epoch(group_id.to_vec(), epoch_id).await
     }
 
     async fn write(
@@ -119,30 +265,56 @@ impl mls_rs_core::group::GroupStateStorage for ClientGroupStorage {
         inserts: Vec<mls_rs_core::group::EpochRecord>,
         updates: Vec<mls_rs_core::group::EpochRecord>,
     ) -> Result<(), Self::Error> {
-        self.0
+        let prune_before = self
+            .max_stored_epochs
+            .zip(inserts.iter().map(|epoch| epoch.id).max())
+            .map(|(max_stored_epochs, max_epoch_id)| {
+                (max_epoch_id + 1).saturating_sub(max_stored_epochs.into())
+            })
+            .filter(|&before_epoch_id| before_epoch_id > 0);
+        let data = match self.compress_group_state {
+            true => Self::compress(&state.data)?,
+            false => state.data,
+        };
+        let bytes = data.len()
+            + inserts
+                .iter()
+                .chain(&updates)
+                .map(|epoch| epoch.data.len())
+                .sum::<usize>();
+
+        self.storage()
             .write(
                 state.id,
-                state.data,
+                data,
                 inserts.into_iter().map(Into::into).collect(),
                 updates.into_iter().map(Into::into).collect(),
+                prune_before,
             )
-            .await
+            .await?;
+        self.metrics.report(MetricFFI::StorageWrite {
+            bytes: bytes as u64,
+        });
+        Ok(())
     }
 
     async fn max_epoch_id(&self, group_id: &[u8]) -> Result<Option<u64>, Self::Error> {
-        self.0.max_epoch_id(group_id.to_vec()).await
+        self.storage().max_epoch_id(group_id.to_vec()).await
     }
 }
 
 pub type UniFFIConfig = client_builder::WithIdentityProvider<
     IdentityProviderStorage,
     client_builder::WithCryptoProvider<
-        CryptoKitProvider,
+        CryptoProviderWrapper,
         WithKeyPackageRepo<
             ClientKeyPackageStorage,
             WithGroupStateStorage<
                 ClientGroupStorage,
-                WithPskStore<PreSharedKeyStorageWrapper, client_builder::BaseConfig>,
+                WithPskStore<
+                    PreSharedKeyStorageWrapper,
+                    WithMlsRules<UniFFIMlsRules, client_builder::BaseConfig>,
+                >,
             >,
         >,
     >,
@@ -153,10 +325,144 @@ pub struct ClientConfigFFI {
     pub client_keypackage_storage: Arc<dyn KeyPackageStorageProtocol>,
     pub group_state_storage: Arc<dyn GroupStateStorageProtocol>,
     pub identity_provider_storage: Arc<dyn IdentityProviderProtocol>,
+    /// Storage of external pre-shared keys, looked up when a commit
+    /// includes a PSK proposal. See [`pre_shared_key_storage_default`].
     pub pre_shared_key_storage: Arc<dyn PreSharedKeyStorageProtocol>,
+    /// Crypto provider used by the client. Defaults to the backend
+    /// selected by the crate features, CryptoKit unless disabled.
+    pub crypto_provider: Arc<dyn CryptoProviderProtocol>,
+    /// Treat signature secret keys as key references, e.g., the data
+    /// representation of a Secure Enclave key, which the resolver uses
+    /// to sign. The private key never exists in process memory.
+    ///
+    /// Signature keys must then be created by the app, so
+    /// [`generate_signature_keypair`](crate::client::generate_signature_keypair)
+    /// can't be used for them.
+    pub signing_key_resolver: Option<Arc<dyn SigningKeyResolverProtocol>>,
     /// Use the ratchet tree extension. If this is false, then you
     /// must supply `ratchet_tree` out of band to clients.
     pub use_ratchet_tree_extension: bool,
+    /// Send a single welcome message for all members added by a
+    /// commit instead of one per member.
+    ///
+    /// [`CommitOutputFFI::welcome_message`](crate::group::CommitOutputFFI::welcome_message)
+    /// only holds the first welcome message, so this should stay
+    /// enabled.
+    pub single_welcome_message: bool,
+    /// Always include a path update in commits, even when the
+    /// committed proposals don't require one.
+    pub path_required: bool,
+    /// Include a GroupInfo with the external public key in the output
+    /// of our commits, see
+    /// [`CommitOutputFFI::group_info`](crate::group::CommitOutputFFI::group_info).
+    ///
+    /// The key lets anyone holding the GroupInfo join with an external
+    /// commit, so leave this disabled if external commits are not
+    /// used.
+    pub allow_external_commit: bool,
+    /// Maximum number of members in a group. Commits we create that
+    /// would exceed it fail with
+    /// [`MlSrsError::GroupSizeLimitExceeded`].
+    pub max_group_size: Option<u32>,
+    /// Maximum size in bytes of the authenticated data of messages we
+    /// send. Larger authenticated data fails with
+    /// [`MlSrsError::AuthenticatedDataTooLarge`].
+    pub max_authenticated_data_size: Option<u32>,
+    /// Application policy for proposals, commits and encryption,
+    /// applied after the options above.
+    pub mls_rules: Option<Arc<dyn MlsRulesProtocol>>,
+    /// Validates the proposals of every commit we send or receive.
+    pub proposal_validator: Option<Arc<dyn ProposalValidatorProtocol>>,
+    /// How custom proposals must be included in commits. Commits we
+    /// send leave out disallowed custom proposals, received commits
+    /// including them fail with
+    /// [`MlSrsError::CustomProposalNotAllowed`].
+    pub custom_proposal_policies: Vec<CustomProposalPolicyFFI>,
+    /// Disallow custom proposals without a policy in
+    /// [`Self::custom_proposal_policies`].
+    pub reject_unknown_custom_proposals: bool,
+    /// Receives timings of commits, message processing and crypto
+    /// operations, and the size of storage writes.
+    pub metrics: Option<Arc<dyn MetricsProtocol>>,
+    /// Extension types advertised in the capabilities of our leaf
+    /// nodes, in addition to the ones mls-rs supports by default.
+    ///
+    /// Credential types are taken from
+    /// [`IdentityProviderProtocol::supported_types`] and cipher
+    /// suites from the crypto provider.
+    pub extension_types: Vec<u16>,
+    /// Custom proposal types advertised in the capabilities of our
+    /// leaf nodes.
+    pub custom_proposal_types: Vec<u16>,
+    /// Protocol version used for new groups and key packages, see
+    /// [`KeyPackageFFI::get_version`](crate::message::KeyPackageFFI::get_version).
+    pub protocol_version: ProtocolVersion,
+    /// Send commits and proposals as encrypted private messages. If
+    /// this is false, they are sent as public messages which the
+    /// delivery service can read.
+    pub encrypt_control_messages: bool,
+    /// Number of epoch records kept per group. Older records are
+    /// pruned by [`GroupStateStorageProtocol::write`] as part of
+    /// each write. If this is `None`, the default, retention is left
+    /// to the storage.
+    ///
+    /// The value is raised to `max_past_epochs + 1` if it is smaller,
+    /// so the records needed for [`Self::max_past_epochs`] are kept.
+    pub max_stored_epochs: Option<u32>,
+    /// Number of past epochs whose messages are still decrypted.
+    /// Older messages fail with [`MlSrsError::WrongEpoch`]. Use `0`
+    /// to only accept messages of the current epoch.
+    ///
+    /// Messages of a past epoch can only be decrypted while its
+    /// record is stored, so [`Self::max_stored_epochs`] should be
+    /// larger. If this is `None`, only the stored records limit it.
+    pub max_past_epochs: Option<u32>,
+    /// Compress group states before writing them to
+    /// [`Self::group_state_storage`]. Compressed and uncompressed
+    /// states are both read back transparently, so this can be
+    /// toggled for existing storages.
+    pub compress_group_state: bool,
+    /// When to generate new key packages, see
+    /// [`ClientFFI::replenish_key_packages`](crate::client::ClientFFI::replenish_key_packages).
+    pub key_package_policy: Option<KeyPackagePolicyFFI>,
+    /// Prefix added to all group ids and key package ids before they
+    /// reach [`Self::group_state_storage`] and
+    /// [`Self::client_keypackage_storage`].
+    ///
+    /// This lets several clients share a storage without seeing each
+    /// other's groups and key packages. The namespace is stored with
+    /// its length, so `acct1` never matches the ids of `acct10`.
+    pub storage_namespace: Option<Vec<u8>>,
+}
+
+impl ClientConfigFFI {
+    /// Apply [`Self::storage_namespace`] to the storages.
+    ///
+    /// The namespace is consumed, so calling this again on the result
+    /// doesn't nest namespaces.
+    pub(crate) fn with_namespaced_storage(mut self) -> Self {
+        if let Some(namespace) = self.storage_namespace.take() {
+            self.group_state_storage = Arc::new(NamespacedStorage::new(
+                self.group_state_storage,
+                namespace.clone(),
+            ));
+            self.client_keypackage_storage = Arc::new(NamespacedStorage::new(
+                self.client_keypackage_storage,
+                namespace,
+            ));
+        }
+        self
+    }
+}
+
+/// Keeps a supply of unconsumed key packages in
+/// [`ClientConfigFFI::client_keypackage_storage`].
+#[derive(Clone, Copy, Debug, uniffi::Record)]
+pub struct KeyPackagePolicyFFI {
+    /// Generate new key packages once fewer than this many remain.
+    pub threshold: u32,
+    /// Number of key packages to have after replenishing.
+    pub target_count: u32,
 }
 
 impl Default for ClientConfigFFI {
@@ -168,11 +474,30 @@ impl Default for ClientConfigFFI {
             group_state_storage: Arc::new(GroupStateStorageAdapter::new(
                 InMemoryGroupStateStorage::new(),
             )),
-            pre_shared_key_storage: Arc::new(PreSharedKeyStorageAdapter::new(
-                InMemoryPreSharedKeyStorage::default(),
-            )),
+            pre_shared_key_storage: pre_shared_key_storage_default(),
             identity_provider_storage: Arc::new(BasicIdentityProviderShim::new()),
+            crypto_provider: default_crypto_provider(),
+            signing_key_resolver: None,
             use_ratchet_tree_extension: true,
+            single_welcome_message: true,
+            path_required: false,
+            allow_external_commit: false,
+            max_group_size: None,
+            max_authenticated_data_size: None,
+            mls_rules: None,
+            proposal_validator: None,
+            custom_proposal_policies: Vec::new(),
+            reject_unknown_custom_proposals: false,
+            metrics: None,
+            extension_types: Vec::new(),
+            custom_proposal_types: Vec::new(),
+            protocol_version: ProtocolVersion::Mls10,
+            encrypt_control_messages: true,
+            max_stored_epochs: None,
+            max_past_epochs: None,
+            compress_group_state: false,
+            key_package_policy: None,
+            storage_namespace: None,
         }
     }
 }
@@ -185,6 +510,14 @@ pub fn client_config_default() -> ClientConfigFFI {
     ClientConfigFFI::default()
 }
 
+/// Create an in-memory pre-shared key storage.
+#[uniffi::export]
+pub fn pre_shared_key_storage_default() -> Arc<dyn PreSharedKeyStorageProtocol> {
+    Arc::new(PreSharedKeyStorageAdapter::new(
+        InMemoryPreSharedKeyStorage::default(),
+    ))
+}
+
 // /// Adapt an IdentityProvider
 // /// The default BasicCredential Identity Provider asserts identity equality
 // /// For Germ, the basic credential is just an anchor into our evolving identity architecture
@@ -201,7 +534,8 @@ impl From<identity::SigningIdentity> for SigningIdentityFFI {
     }
 }
 
-#[maybe_async::must_be_sync]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 #[uniffi::export]
 impl SigningIdentityFFI {
     #[uniffi::constructor]
@@ -249,23 +583,69 @@ impl From<SignaturePublicKeyFFI> for mls_rs::crypto::SignaturePublicKey {
     }
 }
 
+/// Secret bytes which are zeroized when dropped.
+///
+/// A byte array in the bindings. Records can't zeroize themselves on
+/// drop since UniFFI moves their fields out when passing them to
+/// foreign code, so their secret fields do it instead.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretBytes(Zeroizing<Vec<u8>>);
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(Zeroizing::new(bytes))
+    }
+}
+
+impl ZeroizeOnDrop for SecretBytes {}
+
+uniffi::custom_type!(SecretBytes, Vec<u8>, {
+    lower: |secret| secret.to_vec(),
+    try_lift: |bytes| Ok(SecretBytes::from(bytes)),
+});
+
 /// A [`mls_rs::crypto::SignatureSecretKey`] wrapper.
-#[derive(Clone, Debug, uniffi::Record)]
+///
+/// The bytes are zeroized when the key is dropped.
+#[derive(Clone, uniffi::Record)]
 pub struct SignatureSecretKeyFFI {
-    pub bytes: Vec<u8>,
+    pub bytes: SecretBytes,
 }
 
+impl Debug for SignatureSecretKeyFFI {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignatureSecretKeyFFI")
+            .finish_non_exhaustive()
+    }
+}
+
+impl ZeroizeOnDrop for SignatureSecretKeyFFI {}
+
 impl From<mls_rs::crypto::SignatureSecretKey> for SignatureSecretKeyFFI {
     fn from(secret_key: mls_rs::crypto::SignatureSecretKey) -> Self {
+        (&secret_key).into()
+    }
+}
+
+impl From<&mls_rs::crypto::SignatureSecretKey> for SignatureSecretKeyFFI {
+    fn from(secret_key: &mls_rs::crypto::SignatureSecretKey) -> Self {
         Self {
-            bytes: secret_key.as_bytes().to_vec(),
+            bytes: secret_key.as_bytes().to_vec().into(),
         }
     }
 }
 
 impl From<SignatureSecretKeyFFI> for mls_rs::crypto::SignatureSecretKey {
     fn from(secret_key: SignatureSecretKeyFFI) -> Self {
-        Self::new(secret_key.bytes)
+        Self::new(secret_key.bytes.to_vec())
     }
 }
 
@@ -279,8 +659,10 @@ pub struct SignatureKeypairFFI {
 
 /// Identity system that can be used to validate a
 /// [`SigningIdentity`](mls-rs-core::identity::SigningIdentity)
-#[maybe_async::must_be_sync]
-#[uniffi::export(with_foreign)]
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
 pub trait IdentityProviderProtocol: Send + Sync + Debug {
     /// Determine if `signing_identity` is valid for a group member.
     ///
@@ -343,7 +725,8 @@ impl From<Arc<dyn IdentityProviderProtocol>> for IdentityProviderStorage {
     }
 }
 
-#[maybe_async::must_be_sync]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 impl mls_rs_core::identity::IdentityProvider for IdentityProviderStorage {
     type Error = MlSrsError;
 
@@ -353,11 +736,13 @@ impl mls_rs_core::identity::IdentityProvider for IdentityProviderStorage {
         timestamp: Option<MlsTime>,
         context: mls_rs_core::identity::MemberValidationContext,
     ) -> Result<(), Self::Error> {
-        self.0.validate_member(
-            Arc::new(signing_identity.clone().into()),
-            timestamp.map(|t| t.seconds_since_epoch()),
-            context.try_into()?,
-        )
+        self.0
+            .validate_member(
+                Arc::new(signing_identity.clone().into()),
+                timestamp.map(|t| t.seconds_since_epoch()),
+                context.try_into()?,
+            )
+            .await
     }
 
     /// Determine if `signing_identity` is valid for an external sender in
@@ -366,32 +751,36 @@ impl mls_rs_core::identity::IdentityProvider for IdentityProviderStorage {
     /// A `timestamp` value can optionally be supplied to aid with validation
     /// of a [`Credential`](mls-rs-core::identity::Credential) that requires
     /// time based context. For example, X.509 certificates can become expired.
-    fn validate_external_sender(
+    async fn validate_external_sender(
         &self,
         signing_identity: &identity::SigningIdentity,
         timestamp: Option<MlsTime>,
         extensions: Option<&mls_rs::ExtensionList>,
     ) -> Result<(), MlSrsError> {
-        self.0.validate_external_sender(
-            Arc::new(signing_identity.clone().into()),
-            timestamp.map(|t| t.seconds_since_epoch()),
-            extensions.map(|e| Arc::new(e.clone().into())),
-        )
+        self.0
+            .validate_external_sender(
+                Arc::new(signing_identity.clone().into()),
+                timestamp.map(|t| t.seconds_since_epoch()),
+                extensions.map(|e| Arc::new(e.clone().into())),
+            )
+            .await
     }
 
     /// A unique identifier for `signing_identity`.
     ///
     /// The MLS protocol requires that each member of a group has a unique
     /// set of identifiers according to the application.
-    fn identity(
+    async fn identity(
         &self,
         signing_identity: &identity::SigningIdentity,
         extensions: &mls_rs::ExtensionList,
     ) -> Result<Vec<u8>, MlSrsError> {
-        self.0.identity(
-            Arc::new(signing_identity.clone().into()),
-            Arc::new(extensions.clone().into()),
-        )
+        self.0
+            .identity(
+                Arc::new(signing_identity.clone().into()),
+                Arc::new(extensions.clone().into()),
+            )
+            .await
     }
 
     /// Determines if `successor` can remove `predecessor` as part of an external commit.
@@ -400,17 +789,19 @@ impl mls_rs_core::identity::IdentityProvider for IdentityProviderStorage {
     /// new member via external commit. This function determines if a removal
     /// should be allowed by providing the target member to be removed as
     /// `predecessor` and the new member as `successor`.
-    fn valid_successor(
+    async fn valid_successor(
         &self,
         predecessor: &identity::SigningIdentity,
         successor: &identity::SigningIdentity,
         extensions: &mls_rs::ExtensionList,
     ) -> Result<bool, MlSrsError> {
-        self.0.valid_successor(
-            Arc::new(predecessor.clone().into()),
-            Arc::new(successor.clone().into()),
-            Arc::new(extensions.clone().into()),
-        )
+        self.0
+            .valid_successor(
+                Arc::new(predecessor.clone().into()),
+                Arc::new(successor.clone().into()),
+                Arc::new(extensions.clone().into()),
+            )
+            .await
     }
 
     fn supported_types(&self) -> Vec<mls_rs::identity::CredentialType> {
@@ -432,8 +823,10 @@ impl BasicIdentityProviderShim {
     }
 }
 
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 impl IdentityProviderProtocol for BasicIdentityProviderShim {
-    fn validate_member(
+    async fn validate_member(
         &self,
         _: Arc<SigningIdentityFFI>,
         _: Option<u64>,
@@ -442,7 +835,7 @@ impl IdentityProviderProtocol for BasicIdentityProviderShim {
         Ok(())
     }
 
-    fn validate_external_sender(
+    async fn validate_external_sender(
         &self,
         _: Arc<SigningIdentityFFI>,
         _: Option<u64>,
@@ -451,7 +844,7 @@ impl IdentityProviderProtocol for BasicIdentityProviderShim {
         Ok(())
     }
 
-    fn identity(
+    async fn identity(
         &self,
         signing_identity: Arc<SigningIdentityFFI>,
         _: Arc<ExtensionListFFI>,
@@ -463,7 +856,7 @@ impl IdentityProviderProtocol for BasicIdentityProviderShim {
         }
     }
 
-    fn valid_successor(
+    async fn valid_successor(
         &self,
         _: Arc<SigningIdentityFFI>,
         _: Arc<SigningIdentityFFI>,
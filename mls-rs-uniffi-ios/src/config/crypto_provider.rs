@@ -0,0 +1,1103 @@
+use mls_rs::crypto::{
+    HpkeCiphertext, HpkeContextR, HpkeContextS, HpkePublicKey, HpkeSecretKey, SignaturePublicKey,
+    SignatureSecretKey,
+};
+use mls_rs::error::IntoAnyError;
+use mls_rs::{CipherSuite, CipherSuiteProvider, CryptoProvider};
+use zeroize::Zeroizing;
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[cfg(not(mls_build_async))]
+use std::sync::Mutex;
+#[cfg(mls_build_async)]
+use tokio::sync::Mutex;
+
+use crate::config::group_context::CipherSuiteFFI;
+use crate::config::metrics::{CryptoOperationFFI, MetricFFI, Metrics};
+use crate::config::{SignatureKeypairFFI, SignaturePublicKeyFFI, SignatureSecretKeyFFI};
+use crate::crypto::{HpkeCiphertextFFI, HpkeKeypairFFI};
+use crate::mls_rs_error::MlSrsError;
+
+/// Cryptographic operations of the cipher suites used by a client.
+///
+/// Implement this to replace the built-in provider, e.g.,
+/// with platform native crypto. Sizes are given in bytes.
+///
+/// This mirrors [`mls_rs::CipherSuiteProvider`], with the cipher
+/// suite passed to every call.
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
+pub trait CryptoProviderProtocol: Send + Sync + Debug {
+    fn supported_cipher_suites(&self) -> Vec<CipherSuiteFFI>;
+
+    async fn hash(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError>;
+
+    async fn mac(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        key: Vec<u8>,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError>;
+
+    async fn aead_seal(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        key: Vec<u8>,
+        data: Vec<u8>,
+        aad: Option<Vec<u8>>,
+        nonce: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError>;
+
+    async fn aead_open(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        key: Vec<u8>,
+        ciphertext: Vec<u8>,
+        aad: Option<Vec<u8>>,
+        nonce: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError>;
+
+    /// `None` if `cipher_suite` is not supported.
+    fn aead_key_size(&self, cipher_suite: CipherSuiteFFI) -> Option<u32>;
+
+    /// `None` if `cipher_suite` is not supported.
+    fn aead_nonce_size(&self, cipher_suite: CipherSuiteFFI) -> Option<u32>;
+
+    async fn kdf_extract(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        salt: Vec<u8>,
+        ikm: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError>;
+
+    async fn kdf_expand(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        prk: Vec<u8>,
+        info: Vec<u8>,
+        len: u32,
+    ) -> Result<Vec<u8>, MlSrsError>;
+
+    /// `None` if `cipher_suite` is not supported.
+    fn kdf_extract_size(&self, cipher_suite: CipherSuiteFFI) -> Option<u32>;
+
+    async fn hpke_seal(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        remote_key: Vec<u8>,
+        info: Vec<u8>,
+        aad: Option<Vec<u8>>,
+        plaintext: Vec<u8>,
+    ) -> Result<HpkeCiphertextFFI, MlSrsError>;
+
+    async fn hpke_open(
+        &self,
+        keypair: HpkeKeypairFFI,
+        ciphertext: HpkeCiphertextFFI,
+        info: Vec<u8>,
+        aad: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, MlSrsError>;
+
+    async fn hpke_setup_s(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        remote_key: Vec<u8>,
+        info: Vec<u8>,
+    ) -> Result<HpkeSenderSetupFFI, MlSrsError>;
+
+    async fn hpke_setup_r(
+        &self,
+        keypair: HpkeKeypairFFI,
+        kem_output: Vec<u8>,
+        info: Vec<u8>,
+    ) -> Result<Arc<dyn HpkeReceiverContextProtocol>, MlSrsError>;
+
+    async fn kem_derive(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        ikm: Vec<u8>,
+    ) -> Result<HpkeKeypairFFI, MlSrsError>;
+
+    async fn kem_generate(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+    ) -> Result<HpkeKeypairFFI, MlSrsError>;
+
+    fn kem_public_key_validate(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        key: Vec<u8>,
+    ) -> Result<(), MlSrsError>;
+
+    fn random_bytes(&self, cipher_suite: CipherSuiteFFI, len: u32) -> Result<Vec<u8>, MlSrsError>;
+
+    async fn signature_key_generate(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+    ) -> Result<SignatureKeypairFFI, MlSrsError>;
+
+    async fn signature_key_derive_public(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        secret_key: SignatureSecretKeyFFI,
+    ) -> Result<SignaturePublicKeyFFI, MlSrsError>;
+
+    async fn sign(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        secret_key: SignatureSecretKeyFFI,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError>;
+
+    /// Return an error if `signature` is not valid.
+    async fn verify(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        public_key: SignaturePublicKeyFFI,
+        signature: Vec<u8>,
+        data: Vec<u8>,
+    ) -> Result<(), MlSrsError>;
+}
+
+/// The sending side of an HPKE context.
+///
+/// See [`mls_rs::crypto::HpkeContextS`] for details.
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
+pub trait HpkeSenderContextProtocol: Send + Sync + Debug {
+    async fn seal(&self, aad: Option<Vec<u8>>, data: Vec<u8>) -> Result<Vec<u8>, MlSrsError>;
+
+    async fn export(&self, exporter_context: Vec<u8>, len: u32) -> Result<Vec<u8>, MlSrsError>;
+}
+
+/// The receiving side of an HPKE context.
+///
+/// See [`mls_rs::crypto::HpkeContextR`] for details.
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
+pub trait HpkeReceiverContextProtocol: Send + Sync + Debug {
+    async fn open(&self, aad: Option<Vec<u8>>, ciphertext: Vec<u8>) -> Result<Vec<u8>, MlSrsError>;
+
+    async fn export(&self, exporter_context: Vec<u8>, len: u32) -> Result<Vec<u8>, MlSrsError>;
+}
+
+/// Result of [`CryptoProviderProtocol::hpke_setup_s`].
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct HpkeSenderSetupFFI {
+    pub kem_output: Vec<u8>,
+    pub context: Arc<dyn HpkeSenderContextProtocol>,
+}
+
+/// Adapt a [`CryptoProviderProtocol`] to [`mls_rs::CryptoProvider`].
+///
+/// The supported cipher suites are queried once, when the wrapper is
+/// created.
+#[derive(Debug, Clone)]
+pub struct CryptoProviderWrapper {
+    provider: Arc<dyn CryptoProviderProtocol>,
+    supported_cipher_suites: Vec<CipherSuite>,
+    key_resolver: Option<Arc<dyn SigningKeyResolverProtocol>>,
+    metrics: Metrics,
+}
+
+impl CryptoProviderWrapper {
+    /// Report the duration of signature and HPKE operations to
+    /// `metrics`.
+    pub(crate) fn with_metrics(self, metrics: Metrics) -> Self {
+        Self { metrics, ..self }
+    }
+
+    /// Treat signature secret keys as key references resolved by
+    /// `key_resolver`, see
+    /// [`ClientConfigFFI::signing_key_resolver`](crate::config::ClientConfigFFI::signing_key_resolver).
+    pub(crate) fn with_key_resolver(
+        self,
+        key_resolver: Option<Arc<dyn SigningKeyResolverProtocol>>,
+    ) -> Self {
+        Self {
+            key_resolver,
+            ..self
+        }
+    }
+}
+
+impl From<Arc<dyn CryptoProviderProtocol>> for CryptoProviderWrapper {
+    fn from(provider: Arc<dyn CryptoProviderProtocol>) -> Self {
+        let supported_cipher_suites = provider
+            .supported_cipher_suites()
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        Self {
+            provider,
+            supported_cipher_suites,
+            key_resolver: None,
+            metrics: Metrics::default(),
+        }
+    }
+}
+
+impl CryptoProvider for CryptoProviderWrapper {
+    type CipherSuiteProvider = CipherSuiteProviderWrapper;
+
+    fn supported_cipher_suites(&self) -> Vec<CipherSuite> {
+        self.supported_cipher_suites.clone()
+    }
+
+    fn cipher_suite_provider(
+        &self,
+        cipher_suite: CipherSuite,
+    ) -> Option<Self::CipherSuiteProvider> {
+        if !self.supported_cipher_suites.contains(&cipher_suite) {
+            return None;
+        }
+        let cipher_suite = CipherSuiteFFI::try_from(cipher_suite).ok()?;
+        Some(CipherSuiteProviderWrapper {
+            aead_key_size: self.provider.aead_key_size(cipher_suite)? as usize,
+            aead_nonce_size: self.provider.aead_nonce_size(cipher_suite)? as usize,
+            kdf_extract_size: self.provider.kdf_extract_size(cipher_suite)? as usize,
+            provider: self.provider.clone(),
+            cipher_suite,
+            key_resolver: self.key_resolver.clone(),
+            metrics: self.metrics.clone(),
+        })
+    }
+}
+
+/// A [`CryptoProviderProtocol`] bound to a single cipher suite.
+///
+/// The sizes of the cipher suite are queried once, when the wrapper
+/// is created. With a key resolver, signing goes to the resolver
+/// instead of the provider.
+#[derive(Debug, Clone)]
+pub struct CipherSuiteProviderWrapper {
+    provider: Arc<dyn CryptoProviderProtocol>,
+    cipher_suite: CipherSuiteFFI,
+    key_resolver: Option<Arc<dyn SigningKeyResolverProtocol>>,
+    aead_key_size: usize,
+    aead_nonce_size: usize,
+    kdf_extract_size: usize,
+    metrics: Metrics,
+}
+
+impl CipherSuiteProviderWrapper {
+    fn report<T>(
+        &self,
+        operation: CryptoOperationFFI,
+        start: Instant,
+        result: &Result<T, MlSrsError>,
+    ) {
+        self.metrics
+            .report_timed(start, result, |duration_micros, success| {
+                MetricFFI::CryptoOperation {
+                    operation,
+                    duration_micros,
+                    success,
+                }
+            });
+    }
+
+    fn keypair(&self, secret_key: &HpkeSecretKey, public_key: &HpkePublicKey) -> HpkeKeypairFFI {
+        HpkeKeypairFFI {
+            cipher_suite: self.cipher_suite,
+            public_key: public_key.to_vec(),
+            secret_key: secret_key.to_vec(),
+        }
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl CipherSuiteProvider for CipherSuiteProviderWrapper {
+    type Error = MlSrsError;
+    type HpkeContextS = HpkeSenderContextWrapper;
+    type HpkeContextR = HpkeReceiverContextWrapper;
+
+    fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite.into()
+    }
+
+    async fn hash(&self, data: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        self.provider.hash(self.cipher_suite, data.to_vec()).await
+    }
+
+    async fn mac(&self, key: &[u8], data: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        self.provider
+            .mac(self.cipher_suite, key.to_vec(), data.to_vec())
+            .await
+    }
+
+    async fn aead_seal(
+        &self,
+        key: &[u8],
+        data: &[u8],
+        aad: Option<&[u8]>,
+        nonce: &[u8],
+    ) -> Result<Vec<u8>, Self::Error> {
+        self.provider
+            .aead_seal(
+                self.cipher_suite,
+                key.to_vec(),
+                data.to_vec(),
+                aad.map(<[u8]>::to_vec),
+                nonce.to_vec(),
+            )
+            .await
+    }
+
+    async fn aead_open(
+        &self,
+        key: &[u8],
+        ciphertext: &[u8],
+        aad: Option<&[u8]>,
+        nonce: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, Self::Error> {
+        self.provider
+            .aead_open(
+                self.cipher_suite,
+                key.to_vec(),
+                ciphertext.to_vec(),
+                aad.map(<[u8]>::to_vec),
+                nonce.to_vec(),
+            )
+            .await
+            .map(Zeroizing::new)
+    }
+
+    fn aead_key_size(&self) -> usize {
+        self.aead_key_size
+    }
+
+    fn aead_nonce_size(&self) -> usize {
+        self.aead_nonce_size
+    }
+
+    async fn kdf_expand(
+        &self,
+        prk: &[u8],
+        info: &[u8],
+        len: usize,
+    ) -> Result<Zeroizing<Vec<u8>>, Self::Error> {
+        self.provider
+            .kdf_expand(self.cipher_suite, prk.to_vec(), info.to_vec(), len as u32)
+            .await
+            .map(Zeroizing::new)
+    }
+
+    async fn kdf_extract(
+        &self,
+        salt: &[u8],
+        ikm: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, Self::Error> {
+        self.provider
+            .kdf_extract(self.cipher_suite, salt.to_vec(), ikm.to_vec())
+            .await
+            .map(Zeroizing::new)
+    }
+
+    fn kdf_extract_size(&self) -> usize {
+        self.kdf_extract_size
+    }
+
+    async fn hpke_seal(
+        &self,
+        remote_key: &HpkePublicKey,
+        info: &[u8],
+        aad: Option<&[u8]>,
+        pt: &[u8],
+    ) -> Result<HpkeCiphertext, Self::Error> {
+        let start = Instant::now();
+        let result = self
+            .provider
+            .hpke_seal(
+                self.cipher_suite,
+                remote_key.to_vec(),
+                info.to_vec(),
+                aad.map(<[u8]>::to_vec),
+                pt.to_vec(),
+            )
+            .await;
+        self.report(CryptoOperationFFI::HpkeSeal, start, &result);
+        result.map(Into::into)
+    }
+
+    async fn hpke_open(
+        &self,
+        ciphertext: &HpkeCiphertext,
+        local_secret: &HpkeSecretKey,
+        local_public: &HpkePublicKey,
+        info: &[u8],
+        aad: Option<&[u8]>,
+    ) -> Result<Vec<u8>, Self::Error> {
+        let start = Instant::now();
+        let result = self
+            .provider
+            .hpke_open(
+                self.keypair(local_secret, local_public),
+                ciphertext.clone().into(),
+                info.to_vec(),
+                aad.map(<[u8]>::to_vec),
+            )
+            .await;
+        self.report(CryptoOperationFFI::HpkeOpen, start, &result);
+        result
+    }
+
+    async fn hpke_setup_s(
+        &self,
+        remote_key: &HpkePublicKey,
+        info: &[u8],
+    ) -> Result<(Vec<u8>, Self::HpkeContextS), Self::Error> {
+        let setup = self
+            .provider
+            .hpke_setup_s(self.cipher_suite, remote_key.to_vec(), info.to_vec())
+            .await?;
+        Ok((setup.kem_output, HpkeSenderContextWrapper(setup.context)))
+    }
+
+    async fn hpke_setup_r(
+        &self,
+        kem_output: &[u8],
+        local_secret: &HpkeSecretKey,
+        local_public: &HpkePublicKey,
+        info: &[u8],
+    ) -> Result<Self::HpkeContextR, Self::Error> {
+        self.provider
+            .hpke_setup_r(
+                self.keypair(local_secret, local_public),
+                kem_output.to_vec(),
+                info.to_vec(),
+            )
+            .await
+            .map(HpkeReceiverContextWrapper)
+    }
+
+    async fn kem_derive(&self, ikm: &[u8]) -> Result<(HpkeSecretKey, HpkePublicKey), Self::Error> {
+        let keypair = self
+            .provider
+            .kem_derive(self.cipher_suite, ikm.to_vec())
+            .await?;
+        Ok((keypair.secret_key.into(), keypair.public_key.into()))
+    }
+
+    async fn kem_generate(&self) -> Result<(HpkeSecretKey, HpkePublicKey), Self::Error> {
+        let keypair = self.provider.kem_generate(self.cipher_suite).await?;
+        Ok((keypair.secret_key.into(), keypair.public_key.into()))
+    }
+
+    fn kem_public_key_validate(&self, key: &HpkePublicKey) -> Result<(), Self::Error> {
+        self.provider
+            .kem_public_key_validate(self.cipher_suite, key.to_vec())
+    }
+
+    fn random_bytes(&self, out: &mut [u8]) -> Result<(), Self::Error> {
+        let bytes = self
+            .provider
+            .random_bytes(self.cipher_suite, out.len() as u32)?;
+        if bytes.len() != out.len() {
+            return Err(MlSrsError::UnexpectedCallbackError {
+                message: "wrong number of random bytes".to_string(),
+            });
+        }
+        out.copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    async fn signature_key_generate(
+        &self,
+    ) -> Result<(SignatureSecretKey, SignaturePublicKey), Self::Error> {
+        // Keys behind a resolver are created by the app.
+        if self.key_resolver.is_some() {
+            return Err(MlSrsError::NotImplemented);
+        }
+        let keypair = self
+            .provider
+            .signature_key_generate(self.cipher_suite)
+            .await?;
+        Ok((keypair.secret_key.into(), keypair.public_key.into()))
+    }
+
+    async fn signature_key_derive_public(
+        &self,
+        secret_key: &SignatureSecretKey,
+    ) -> Result<SignaturePublicKey, Self::Error> {
+        let public_key = match &self.key_resolver {
+            Some(key_resolver) => {
+                key_resolver
+                    .public_key(self.cipher_suite, secret_key.as_bytes().to_vec())
+                    .await?
+            }
+            None => {
+                self.provider
+                    .signature_key_derive_public(self.cipher_suite, secret_key.into())
+                    .await?
+            }
+        };
+        Ok(public_key.into())
+    }
+
+    async fn sign(
+        &self,
+        secret_key: &SignatureSecretKey,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Self::Error> {
+        let start = Instant::now();
+        let result = match &self.key_resolver {
+            Some(key_resolver) => {
+                key_resolver
+                    .sign(
+                        self.cipher_suite,
+                        secret_key.as_bytes().to_vec(),
+                        data.to_vec(),
+                    )
+                    .await
+            }
+            None => {
+                self.provider
+                    .sign(self.cipher_suite, secret_key.into(), data.to_vec())
+                    .await
+            }
+        };
+        self.report(CryptoOperationFFI::Sign, start, &result);
+        result
+    }
+
+    async fn verify(
+        &self,
+        public_key: &SignaturePublicKey,
+        signature: &[u8],
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let result = self
+            .provider
+            .verify(
+                self.cipher_suite,
+                public_key.clone().into(),
+                signature.to_vec(),
+                data.to_vec(),
+            )
+            .await;
+        self.report(CryptoOperationFFI::Verify, start, &result);
+        result
+    }
+}
+
+/// Adapt a [`HpkeSenderContextProtocol`] to
+/// [`mls_rs::crypto::HpkeContextS`].
+#[derive(Debug)]
+pub struct HpkeSenderContextWrapper(Arc<dyn HpkeSenderContextProtocol>);
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl HpkeContextS for HpkeSenderContextWrapper {
+    type Error = MlSrsError;
+
+    async fn seal(&mut self, aad: Option<&[u8]>, data: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        self.0.seal(aad.map(<[u8]>::to_vec), data.to_vec()).await
+    }
+
+    async fn export(&self, exporter_context: &[u8], len: usize) -> Result<Vec<u8>, Self::Error> {
+        self.0.export(exporter_context.to_vec(), len as u32).await
+    }
+}
+
+/// Adapt a [`HpkeReceiverContextProtocol`] to
+/// [`mls_rs::crypto::HpkeContextR`].
+#[derive(Debug)]
+pub struct HpkeReceiverContextWrapper(Arc<dyn HpkeReceiverContextProtocol>);
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl HpkeContextR for HpkeReceiverContextWrapper {
+    type Error = MlSrsError;
+
+    async fn open(
+        &mut self,
+        aad: Option<&[u8]>,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Self::Error> {
+        self.0
+            .open(aad.map(<[u8]>::to_vec), ciphertext.to_vec())
+            .await
+    }
+
+    async fn export(&self, exporter_context: &[u8], len: usize) -> Result<Vec<u8>, Self::Error> {
+        self.0.export(exporter_context.to_vec(), len as u32).await
+    }
+}
+
+/// Resolves signature key references to keys held outside of the
+/// process, e.g., in the Secure Enclave.
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
+pub trait SigningKeyResolverProtocol: Send + Sync + Debug {
+    /// Public key of the key referenced by `key_reference`.
+    async fn public_key(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        key_reference: Vec<u8>,
+    ) -> Result<SignaturePublicKeyFFI, MlSrsError>;
+
+    /// Sign `data` with the key referenced by `key_reference`.
+    async fn sign(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        key_reference: Vec<u8>,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError>;
+}
+
+/// Signs on behalf of a client, see [`ClientFFI::new_with_signer`].
+///
+/// [`ClientFFI::new_with_signer`]: crate::client::ClientFFI::new_with_signer
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
+pub trait SignerProtocol: Send + Sync + Debug {
+    /// Sign `data` with the client's signature key.
+    ///
+    /// `data` is the complete content to be signed, including the MLS
+    /// signature label.
+    async fn sign(&self, data: Vec<u8>) -> Result<Vec<u8>, MlSrsError>;
+
+    /// Public key of the client's signature key.
+    async fn public_key(&self) -> Result<SignaturePublicKeyFFI, MlSrsError>;
+}
+
+/// Resolve every key reference to a single [`SignerProtocol`].
+#[derive(Debug)]
+pub(crate) struct SignerResolver(Arc<dyn SignerProtocol>);
+
+impl From<Arc<dyn SignerProtocol>> for SignerResolver {
+    fn from(signer: Arc<dyn SignerProtocol>) -> Self {
+        Self(signer)
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl SigningKeyResolverProtocol for SignerResolver {
+    async fn public_key(
+        &self,
+        _cipher_suite: CipherSuiteFFI,
+        _key_reference: Vec<u8>,
+    ) -> Result<SignaturePublicKeyFFI, MlSrsError> {
+        self.0.public_key().await
+    }
+
+    async fn sign(
+        &self,
+        _cipher_suite: CipherSuiteFFI,
+        _key_reference: Vec<u8>,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError> {
+        self.0.sign(data).await
+    }
+}
+
+/// Adapt a mls-rs `CryptoProvider` implementation.
+///
+/// This is used to adapt a mls-rs `CryptoProvider` implementation to
+/// our own `CryptoProviderProtocol` trait. This way we can use any
+/// standard mls-rs crypto provider from the FFI layer.
+#[derive(Debug)]
+pub(crate) struct CryptoProviderAdapter<P>(P);
+
+impl<P> CryptoProviderAdapter<P> {
+    pub fn new(crypto_provider: P) -> CryptoProviderAdapter<P> {
+        Self(crypto_provider)
+    }
+}
+
+impl<P: CryptoProvider> CryptoProviderAdapter<P> {
+    fn cipher_suite_provider(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+    ) -> Result<P::CipherSuiteProvider, MlSrsError> {
+        self.0
+            .cipher_suite_provider(cipher_suite.into())
+            .ok_or(mls_rs::error::MlsError::UnsupportedCipherSuite(cipher_suite.into()).into())
+    }
+}
+
+fn keypair_parts(keypair: HpkeKeypairFFI) -> (CipherSuiteFFI, HpkeSecretKey, HpkePublicKey) {
+    (
+        keypair.cipher_suite,
+        keypair.secret_key.into(),
+        keypair.public_key.into(),
+    )
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl<P> CryptoProviderProtocol for CryptoProviderAdapter<P>
+where
+    P: CryptoProvider + Debug,
+    <P::CipherSuiteProvider as CipherSuiteProvider>::HpkeContextS: Send + 'static,
+    <P::CipherSuiteProvider as CipherSuiteProvider>::HpkeContextR: Send + 'static,
+{
+    fn supported_cipher_suites(&self) -> Vec<CipherSuiteFFI> {
+        self.0
+            .supported_cipher_suites()
+            .into_iter()
+            .flat_map(CipherSuiteFFI::try_from)
+            .collect()
+    }
+
+    async fn hash(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .hash(&data)
+            .await
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn mac(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        key: Vec<u8>,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .mac(&key, &data)
+            .await
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn aead_seal(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        key: Vec<u8>,
+        data: Vec<u8>,
+        aad: Option<Vec<u8>>,
+        nonce: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .aead_seal(&key, &data, aad.as_deref(), &nonce)
+            .await
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn aead_open(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        key: Vec<u8>,
+        ciphertext: Vec<u8>,
+        aad: Option<Vec<u8>>,
+        nonce: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .aead_open(&key, &ciphertext, aad.as_deref(), &nonce)
+            .await
+            .map(|plaintext| plaintext.to_vec())
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    fn aead_key_size(&self, cipher_suite: CipherSuiteFFI) -> Option<u32> {
+        self.cipher_suite_provider(cipher_suite)
+            .ok()
+            .map(|provider| provider.aead_key_size() as u32)
+    }
+
+    fn aead_nonce_size(&self, cipher_suite: CipherSuiteFFI) -> Option<u32> {
+        self.cipher_suite_provider(cipher_suite)
+            .ok()
+            .map(|provider| provider.aead_nonce_size() as u32)
+    }
+
+    async fn kdf_extract(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        salt: Vec<u8>,
+        ikm: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .kdf_extract(&salt, &ikm)
+            .await
+            .map(|prk| prk.to_vec())
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn kdf_expand(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        prk: Vec<u8>,
+        info: Vec<u8>,
+        len: u32,
+    ) -> Result<Vec<u8>, MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .kdf_expand(&prk, &info, len as usize)
+            .await
+            .map(|okm| okm.to_vec())
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    fn kdf_extract_size(&self, cipher_suite: CipherSuiteFFI) -> Option<u32> {
+        self.cipher_suite_provider(cipher_suite)
+            .ok()
+            .map(|provider| provider.kdf_extract_size() as u32)
+    }
+
+    async fn hpke_seal(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        remote_key: Vec<u8>,
+        info: Vec<u8>,
+        aad: Option<Vec<u8>>,
+        plaintext: Vec<u8>,
+    ) -> Result<HpkeCiphertextFFI, MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .hpke_seal(&remote_key.into(), &info, aad.as_deref(), &plaintext)
+            .await
+            .map(Into::into)
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn hpke_open(
+        &self,
+        keypair: HpkeKeypairFFI,
+        ciphertext: HpkeCiphertextFFI,
+        info: Vec<u8>,
+        aad: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, MlSrsError> {
+        let (cipher_suite, secret_key, public_key) = keypair_parts(keypair);
+        self.cipher_suite_provider(cipher_suite)?
+            .hpke_open(
+                &ciphertext.into(),
+                &secret_key,
+                &public_key,
+                &info,
+                aad.as_deref(),
+            )
+            .await
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn hpke_setup_s(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        remote_key: Vec<u8>,
+        info: Vec<u8>,
+    ) -> Result<HpkeSenderSetupFFI, MlSrsError> {
+        let (kem_output, context) = self
+            .cipher_suite_provider(cipher_suite)?
+            .hpke_setup_s(&remote_key.into(), &info)
+            .await
+            .map_err(|err| err.into_any_error())?;
+        Ok(HpkeSenderSetupFFI {
+            kem_output,
+            context: Arc::new(HpkeContextAdapter::new(context)),
+        })
+    }
+
+    async fn hpke_setup_r(
+        &self,
+        keypair: HpkeKeypairFFI,
+        kem_output: Vec<u8>,
+        info: Vec<u8>,
+    ) -> Result<Arc<dyn HpkeReceiverContextProtocol>, MlSrsError> {
+        let (cipher_suite, secret_key, public_key) = keypair_parts(keypair);
+        let context = self
+            .cipher_suite_provider(cipher_suite)?
+            .hpke_setup_r(&kem_output, &secret_key, &public_key, &info)
+            .await
+            .map_err(|err| err.into_any_error())?;
+        Ok(Arc::new(HpkeContextAdapter::new(context)))
+    }
+
+    async fn kem_derive(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        ikm: Vec<u8>,
+    ) -> Result<HpkeKeypairFFI, MlSrsError> {
+        let (secret_key, public_key) = self
+            .cipher_suite_provider(cipher_suite)?
+            .kem_derive(&ikm)
+            .await
+            .map_err(|err| err.into_any_error())?;
+        Ok(HpkeKeypairFFI {
+            cipher_suite,
+            public_key: public_key.to_vec(),
+            secret_key: secret_key.to_vec(),
+        })
+    }
+
+    async fn kem_generate(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+    ) -> Result<HpkeKeypairFFI, MlSrsError> {
+        let (secret_key, public_key) = self
+            .cipher_suite_provider(cipher_suite)?
+            .kem_generate()
+            .await
+            .map_err(|err| err.into_any_error())?;
+        Ok(HpkeKeypairFFI {
+            cipher_suite,
+            public_key: public_key.to_vec(),
+            secret_key: secret_key.to_vec(),
+        })
+    }
+
+    fn kem_public_key_validate(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        key: Vec<u8>,
+    ) -> Result<(), MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .kem_public_key_validate(&key.into())
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    fn random_bytes(&self, cipher_suite: CipherSuiteFFI, len: u32) -> Result<Vec<u8>, MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .random_bytes_vec(len as usize)
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn signature_key_generate(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+    ) -> Result<SignatureKeypairFFI, MlSrsError> {
+        let (secret_key, public_key) = self
+            .cipher_suite_provider(cipher_suite)?
+            .signature_key_generate()
+            .await
+            .map_err(|err| err.into_any_error())?;
+        Ok(SignatureKeypairFFI {
+            cipher_suite,
+            public_key: public_key.into(),
+            secret_key: secret_key.into(),
+        })
+    }
+
+    async fn signature_key_derive_public(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        secret_key: SignatureSecretKeyFFI,
+    ) -> Result<SignaturePublicKeyFFI, MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .signature_key_derive_public(&secret_key.into())
+            .await
+            .map(Into::into)
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn sign(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        secret_key: SignatureSecretKeyFFI,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .sign(&secret_key.into(), &data)
+            .await
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn verify(
+        &self,
+        cipher_suite: CipherSuiteFFI,
+        public_key: SignaturePublicKeyFFI,
+        signature: Vec<u8>,
+        data: Vec<u8>,
+    ) -> Result<(), MlSrsError> {
+        self.cipher_suite_provider(cipher_suite)?
+            .verify(&public_key.into(), &signature, &data)
+            .await
+            .map_err(|err| err.into_any_error().into())
+    }
+}
+
+/// Adapt a mls-rs HPKE context to [`HpkeSenderContextProtocol`] or
+/// [`HpkeReceiverContextProtocol`].
+pub(crate) struct HpkeContextAdapter<C>(Mutex<C>);
+
+impl<C> HpkeContextAdapter<C> {
+    pub fn new(context: C) -> HpkeContextAdapter<C> {
+        Self(Mutex::new(context))
+    }
+
+    #[cfg(not(mls_build_async))]
+    fn inner(&self) -> std::sync::MutexGuard<'_, C> {
+        self.0.lock().unwrap()
+    }
+
+    #[cfg(mls_build_async)]
+    async fn inner(&self) -> tokio::sync::MutexGuard<'_, C> {
+        self.0.lock().await
+    }
+}
+
+impl<C> Debug for HpkeContextAdapter<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HpkeContextAdapter").finish_non_exhaustive()
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl<C, Err> HpkeSenderContextProtocol for HpkeContextAdapter<C>
+where
+    C: HpkeContextS<Error = Err> + Send,
+    Err: IntoAnyError,
+{
+    async fn seal(&self, aad: Option<Vec<u8>>, data: Vec<u8>) -> Result<Vec<u8>, MlSrsError> {
+        self.inner()
+            .await
+            .seal(aad.as_deref(), &data)
+            .await
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn export(&self, exporter_context: Vec<u8>, len: u32) -> Result<Vec<u8>, MlSrsError> {
+        self.inner()
+            .await
+            .export(&exporter_context, len as usize)
+            .await
+            .map_err(|err| err.into_any_error().into())
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl<C, Err> HpkeReceiverContextProtocol for HpkeContextAdapter<C>
+where
+    C: HpkeContextR<Error = Err> + Send,
+    Err: IntoAnyError,
+{
+    async fn open(&self, aad: Option<Vec<u8>>, ciphertext: Vec<u8>) -> Result<Vec<u8>, MlSrsError> {
+        self.inner()
+            .await
+            .open(aad.as_deref(), &ciphertext)
+            .await
+            .map_err(|err| err.into_any_error().into())
+    }
+
+    async fn export(&self, exporter_context: Vec<u8>, len: u32) -> Result<Vec<u8>, MlSrsError> {
+        self.inner()
+            .await
+            .export(&exporter_context, len as usize)
+            .await
+            .map_err(|err| err.into_any_error().into())
+    }
+}
@@ -0,0 +1,185 @@
+use mls_rs::CipherSuiteProvider;
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::config::crypto_provider::{CipherSuiteProviderWrapper, CryptoProviderProtocol};
+use crate::config::group_context::CipherSuiteFFI;
+use crate::config::group_state::{EpochRecordFFI, GroupStateStorageProtocol};
+use crate::crypto::provider_cipher_suite_provider;
+use crate::mls_rs_error::MlSrsError;
+
+/// Supplies the keys used by [`encrypted_group_state_storage`].
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
+pub trait StorageKeyProtocol: Send + Sync + Debug {
+    /// The AEAD key protecting the state of `group_id`.
+    ///
+    /// The key must have the AEAD key size of the cipher suite given
+    /// to [`encrypted_group_state_storage`] and must not change for
+    /// the lifetime of the stored group.
+    fn key(&self, group_id: Vec<u8>) -> Result<Vec<u8>, MlSrsError>;
+}
+
+/// Wrap `inner` so group states and epoch records are encrypted
+/// before they reach it.
+///
+/// Records are sealed with the AEAD of `cipher_suite`, using keys
+/// from `key_provider` and a random nonce stored with each record.
+/// The group id and epoch id are authenticated, so records can't be
+/// swapped between groups or epochs. Group ids are stored in the
+/// clear.
+///
+/// The AEAD and RNG of `crypto_provider` are used, or of the
+/// built-in provider if it is `None`. Pass
+/// [`ClientConfigFFI::crypto_provider`](crate::config::ClientConfigFFI::crypto_provider)
+/// to use the same provider as the client.
+#[uniffi::export(default(crypto_provider = None))]
+pub fn encrypted_group_state_storage(
+    inner: Arc<dyn GroupStateStorageProtocol>,
+    cipher_suite: CipherSuiteFFI,
+    key_provider: Arc<dyn StorageKeyProtocol>,
+    crypto_provider: Option<Arc<dyn CryptoProviderProtocol>>,
+) -> Result<Arc<dyn GroupStateStorageProtocol>, MlSrsError> {
+    Ok(Arc::new(EncryptedGroupStateStorage {
+        inner,
+        cipher_suite_provider: provider_cipher_suite_provider(crypto_provider, cipher_suite)?,
+        key_provider,
+    }))
+}
+
+/// See [`encrypted_group_state_storage`].
+#[derive(Debug)]
+struct EncryptedGroupStateStorage {
+    inner: Arc<dyn GroupStateStorageProtocol>,
+    cipher_suite_provider: CipherSuiteProviderWrapper,
+    key_provider: Arc<dyn StorageKeyProtocol>,
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl EncryptedGroupStateStorage {
+    /// Associated data binding a record to its group and epoch. The
+    /// group state itself uses no epoch.
+    ///
+    /// The group id is length prefixed, so the boundary between group
+    /// id and epoch id is unambiguous.
+    fn aad(group_id: &[u8], epoch_id: Option<u64>) -> Vec<u8> {
+        let mut aad = (group_id.len() as u64).to_be_bytes().to_vec();
+        aad.extend(group_id);
+        aad.extend(epoch_id.map_or([0xff; 8], u64::to_be_bytes));
+        aad
+    }
+
+    async fn seal(
+        &self,
+        group_id: &[u8],
+        epoch_id: Option<u64>,
+        data: &[u8],
+    ) -> Result<Vec<u8>, MlSrsError> {
+        let cipher_suite_provider = &self.cipher_suite_provider;
+        let key = self.key_provider.key(group_id.to_vec())?;
+        let nonce =
+            cipher_suite_provider.random_bytes_vec(cipher_suite_provider.aead_nonce_size())?;
+        let ciphertext = cipher_suite_provider
+            .aead_seal(&key, data, Some(&Self::aad(group_id, epoch_id)), &nonce)
+            .await?;
+        Ok([nonce, ciphertext].concat())
+    }
+
+    async fn open(
+        &self,
+        group_id: &[u8],
+        epoch_id: Option<u64>,
+        data: &[u8],
+    ) -> Result<Vec<u8>, MlSrsError> {
+        let cipher_suite_provider = &self.cipher_suite_provider;
+        let key = self.key_provider.key(group_id.to_vec())?;
+        let nonce_size = cipher_suite_provider.aead_nonce_size();
+        if data.len() < nonce_size {
+            return Err(MlSrsError::UnexpecteMessageFormat);
+        }
+        let (nonce, ciphertext) = data.split_at(nonce_size);
+        let plaintext = cipher_suite_provider
+            .aead_open(
+                &key,
+                ciphertext,
+                Some(&Self::aad(group_id, epoch_id)),
+                nonce,
+            )
+            .await?;
+        Ok(plaintext.to_vec())
+    }
+
+    async fn seal_epoch(
+        &self,
+        group_id: &[u8],
+        epoch: EpochRecordFFI,
+    ) -> Result<EpochRecordFFI, MlSrsError> {
+        Ok(EpochRecordFFI {
+            id: epoch.id,
+            data: self.seal(group_id, Some(epoch.id), &epoch.data).await?,
+        })
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl GroupStateStorageProtocol for EncryptedGroupStateStorage {
+    async fn state(&self, group_id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError> {
+        match self.inner.state(group_id.clone()).await? {
+            Some(data) => Ok(Some(self.open(&group_id, None, &data).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn epoch(&self, group_id: Vec<u8>, epoch_id: u64) -> Result<Option<Vec<u8>>, MlSrsError> {
+        match self.inner.epoch(group_id.clone(), epoch_id).await? {
+            Some(data) => Ok(Some(self.open(&group_id, Some(epoch_id), &data).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn write(
+        &self,
+        group_id: Vec<u8>,
+        group_state: Vec<u8>,
+        epoch_inserts: Vec<EpochRecordFFI>,
+        epoch_updates: Vec<EpochRecordFFI>,
+        prune_before: Option<u64>,
+    ) -> Result<(), MlSrsError> {
+        let group_state = self.seal(&group_id, None, &group_state).await?;
+        let mut sealed_inserts = Vec::with_capacity(epoch_inserts.len());
+        for epoch in epoch_inserts {
+            sealed_inserts.push(self.seal_epoch(&group_id, epoch).await?);
+        }
+        let mut sealed_updates = Vec::with_capacity(epoch_updates.len());
+        for epoch in epoch_updates {
+            sealed_updates.push(self.seal_epoch(&group_id, epoch).await?);
+        }
+
+        self.inner
+            .write(
+                group_id,
+                group_state,
+                sealed_inserts,
+                sealed_updates,
+                prune_before,
+            )
+            .await
+    }
+
+    async fn max_epoch_id(&self, group_id: Vec<u8>) -> Result<Option<u64>, MlSrsError> {
+        self.inner.max_epoch_id(group_id).await
+    }
+
+    async fn delete_group(&self, group_id: Vec<u8>) -> Result<(), MlSrsError> {
+        self.inner.delete_group(group_id).await
+    }
+
+    async fn group_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        self.inner.group_ids().await
+    }
+}
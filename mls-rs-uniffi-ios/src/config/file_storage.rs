@@ -0,0 +1,259 @@
+use mls_rs::mls_rs_codec::{MlsDecode, MlsEncode};
+
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zeroize::{Zeroize, Zeroizing};
+
+#[cfg(not(mls_build_async))]
+use std::sync::Mutex;
+#[cfg(mls_build_async)]
+use tokio::sync::Mutex;
+
+use crate::config::group_state::{
+    EpochRecordFFI, GroupStateStorageProtocol, KeyPackageDataFFI, KeyPackageStorageProtocol,
+};
+use crate::mls_rs_error::MlSrsError;
+
+/// Open a group state storage keeping one directory per group below
+/// `directory`.
+///
+/// Files are replaced atomically, so a crash never leaves a partially
+/// written group state behind.
+#[uniffi::export]
+pub fn file_group_state_storage(
+    directory: String,
+) -> Result<Arc<dyn GroupStateStorageProtocol>, MlSrsError> {
+    Ok(Arc::new(FileStorage::open(directory.into())?))
+}
+
+/// Open a key package storage keeping one file per key package below
+/// `directory`.
+///
+/// The same directory can be used with [`file_group_state_storage`].
+#[uniffi::export]
+pub fn file_key_package_storage(
+    directory: String,
+) -> Result<Arc<dyn KeyPackageStorageProtocol>, MlSrsError> {
+    Ok(Arc::new(FileStorage::open(directory.into())?))
+}
+
+/// Group state and key package storage backed by a directory.
+///
+/// ```text
+/// <directory>/groups/<hex group id>/state
+/// <directory>/groups/<hex group id>/<epoch id>.epoch
+/// <directory>/key_packages/<hex id>
+/// ```
+#[derive(Debug)]
+pub struct FileStorage {
+    directory: PathBuf,
+    /// Serializes writes so temporary files are never shared.
+    lock: Mutex<()>,
+}
+
+impl FileStorage {
+    /// Open or create the storage in `directory`.
+    pub fn open(directory: PathBuf) -> Result<Self, MlSrsError> {
+        fs::create_dir_all(directory.join("groups"))?;
+        fs::create_dir_all(directory.join("key_packages"))?;
+        Ok(Self {
+            directory,
+            lock: Mutex::new(()),
+        })
+    }
+
+    #[cfg(not(mls_build_async))]
+    fn write_lock(&self) -> Result<std::sync::MutexGuard<'_, ()>, MlSrsError> {
+        // Poisoned by a writer which panicked, report it instead of
+        // panicking as well.
+        self.lock.lock().map_err(|_| MlSrsError::IoError {
+            message: "storage lock poisoned".to_string(),
+        })
+    }
+
+    #[cfg(mls_build_async)]
+    async fn write_lock(&self) -> Result<tokio::sync::MutexGuard<'_, ()>, MlSrsError> {
+        Ok(self.lock.lock().await)
+    }
+
+    fn group_directory(&self, group_id: &[u8]) -> PathBuf {
+        self.directory.join("groups").join(hex(group_id))
+    }
+
+    fn epoch_path(&self, group_id: &[u8], epoch_id: u64) -> PathBuf {
+        self.group_directory(group_id)
+            .join(format!("{epoch_id}.epoch"))
+    }
+
+    fn key_package_path(&self, id: &[u8]) -> PathBuf {
+        self.directory.join("key_packages").join(hex(id))
+    }
+
+    fn epoch_ids(&self, group_id: &[u8]) -> Result<Vec<u64>, MlSrsError> {
+        let entries = match fs::read_dir(self.group_directory(group_id)) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut epoch_ids = Vec::new();
+        for entry in entries {
+            let file_name = entry?.file_name();
+            let epoch_id = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".epoch"))
+                .and_then(|id| id.parse().ok());
+            epoch_ids.extend(epoch_id);
+        }
+        Ok(epoch_ids)
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl GroupStateStorageProtocol for FileStorage {
+    async fn state(&self, group_id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError> {
+        read(&self.group_directory(&group_id).join("state"))
+    }
+
+    async fn epoch(&self, group_id: Vec<u8>, epoch_id: u64) -> Result<Option<Vec<u8>>, MlSrsError> {
+        read(&self.epoch_path(&group_id, epoch_id))
+    }
+
+    async fn write(
+        &self,
+        group_id: Vec<u8>,
+        group_state: Vec<u8>,
+        epoch_inserts: Vec<EpochRecordFFI>,
+        epoch_updates: Vec<EpochRecordFFI>,
+        prune_before: Option<u64>,
+    ) -> Result<(), MlSrsError> {
+        let _lock = self.write_lock().await?;
+        let group_directory = self.group_directory(&group_id);
+        if !group_directory.exists() {
+            fs::create_dir(&group_directory)?;
+            sync_directory(&self.directory.join("groups"))?;
+        }
+
+        // Epochs are written first, so the group state never refers
+        // to an epoch which is missing.
+        for epoch in epoch_inserts.into_iter().chain(epoch_updates) {
+            write_atomic(&self.epoch_path(&group_id, epoch.id), &epoch.data)?;
+        }
+        write_atomic(&self.group_directory(&group_id).join("state"), &group_state)?;
+
+        if let Some(before_epoch_id) = prune_before {
+            for epoch_id in self.epoch_ids(&group_id)? {
+                if epoch_id < before_epoch_id {
+                    remove(&self.epoch_path(&group_id, epoch_id))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn max_epoch_id(&self, group_id: Vec<u8>) -> Result<Option<u64>, MlSrsError> {
+        Ok(self.epoch_ids(&group_id)?.into_iter().max())
+    }
+
+    async fn delete_group(&self, group_id: Vec<u8>) -> Result<(), MlSrsError> {
+        let _lock = self.write_lock().await?;
+        match fs::remove_dir_all(self.group_directory(&group_id)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn group_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        list(&self.directory.join("groups"))
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl KeyPackageStorageProtocol for FileStorage {
+    async fn delete(&self, id: Vec<u8>) -> Result<(), MlSrsError> {
+        let _lock = self.write_lock().await?;
+        remove(&self.key_package_path(&id))
+    }
+
+    async fn insert(&self, id: Vec<u8>, mut pkg: KeyPackageDataFFI) -> Result<(), MlSrsError> {
+        let _lock = self.write_lock().await?;
+        let bytes = Zeroizing::new(pkg.mls_encode_to_vec()?);
+        pkg.zeroize();
+        write_atomic(&self.key_package_path(&id), &bytes)
+    }
+
+    async fn get(&self, id: Vec<u8>) -> Result<Option<KeyPackageDataFFI>, MlSrsError> {
+        read(&self.key_package_path(&id))?
+            .map(Zeroizing::new)
+            .map(|bytes| KeyPackageDataFFI::mls_decode(&mut &**bytes))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    async fn key_package_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        list(&self.directory.join("key_packages"))
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unhex(name: &str) -> Option<Vec<u8>> {
+    if name.len() % 2 != 0 {
+        return None;
+    }
+    (0..name.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(name.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn read(path: &Path) -> Result<Option<Vec<u8>>, MlSrsError> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn remove(path: &Path) -> Result<(), MlSrsError> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// List the hex encoded names found in `directory`.
+fn list(directory: &Path) -> Result<Vec<Vec<u8>>, MlSrsError> {
+    let mut ids = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let file_name = entry?.file_name();
+        ids.extend(file_name.to_str().and_then(unhex));
+    }
+    Ok(ids)
+}
+
+/// Write `data` to a temporary file and rename it to `path`.
+///
+/// The parent directory is synced as well, otherwise the rename
+/// itself may be lost in a crash.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<(), MlSrsError> {
+    let temporary_path = path.with_extension("tmp");
+    let mut file = fs::File::create(&temporary_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&temporary_path, path)?;
+    if let Some(parent) = path.parent() {
+        sync_directory(parent)?;
+    }
+    Ok(())
+}
+
+fn sync_directory(directory: &Path) -> Result<(), MlSrsError> {
+    fs::File::open(directory)?.sync_all()?;
+    Ok(())
+}
@@ -1,7 +1,14 @@
+use crate::config::SigningIdentityFFI;
 use crate::mls_rs_error::MlSrsError;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use mls_rs::error::MlsError;
+use mls_rs::error::{IntoAnyError, MlsError};
+use mls_rs::extension::built_in::{ApplicationIdExt, ExternalSendersExt, RequiredCapabilitiesExt};
+use mls_rs::group::ExportedTree;
+use mls_rs::identity::CredentialType;
+use mls_rs::mls_rs_codec::{MlsDecode, MlsEncode};
+use mls_rs_core::extension::{ExtensionType, MlsExtension};
+use mls_rs_core::group::ProposalType;
 
 #[derive(uniffi::Record, Debug, Clone)]
 pub struct GroupContextFFI {
@@ -47,12 +54,16 @@ impl TryFrom<mls_rs_core::group::GroupContext> for GroupContextFFI {
 /// [`mls_rs::CipherSuite`].
 #[derive(Copy, Clone, Debug, uniffi::Enum)]
 pub enum CipherSuiteFFI {
+    Curve25519Aes128,
+    P256Aes128,
     Curve25519ChaCha,
 }
 
 impl From<CipherSuiteFFI> for mls_rs::CipherSuite {
     fn from(cipher_suite: CipherSuiteFFI) -> mls_rs::CipherSuite {
         match cipher_suite {
+            CipherSuiteFFI::Curve25519Aes128 => mls_rs::CipherSuite::CURVE25519_AES128,
+            CipherSuiteFFI::P256Aes128 => mls_rs::CipherSuite::P256_AES128,
             CipherSuiteFFI::Curve25519ChaCha => mls_rs::CipherSuite::CURVE25519_CHACHA,
         }
     }
@@ -63,6 +74,8 @@ impl TryFrom<mls_rs::CipherSuite> for CipherSuiteFFI {
 
     fn try_from(cipher_suite: mls_rs::CipherSuite) -> Result<Self, Self::Error> {
         match cipher_suite {
+            mls_rs::CipherSuite::CURVE25519_AES128 => Ok(CipherSuiteFFI::Curve25519Aes128),
+            mls_rs::CipherSuite::P256_AES128 => Ok(CipherSuiteFFI::P256Aes128),
             mls_rs::CipherSuite::CURVE25519_CHACHA => Ok(CipherSuiteFFI::Curve25519ChaCha),
             _ => Err(MlsError::UnsupportedCipherSuite(cipher_suite))?,
         }
@@ -70,19 +83,166 @@ impl TryFrom<mls_rs::CipherSuite> for CipherSuiteFFI {
 }
 
 /// A [`mls_rs::ExtensionList`] wrapper.
-#[derive(uniffi::Object, Debug, Clone)]
+///
+/// The list can be modified in place, e.g., to assemble the
+/// extensions of a new group.
+#[derive(uniffi::Object, Debug)]
 pub struct ExtensionListFFI {
-    _inner: Vec<ExtensionFFI>,
+    inner: Mutex<mls_rs::ExtensionList>,
+}
+
+impl ExtensionListFFI {
+    fn inner(&self) -> std::sync::MutexGuard<'_, mls_rs::ExtensionList> {
+        self.inner.lock().unwrap()
+    }
+}
+
+impl Clone for ExtensionListFFI {
+    fn clone(&self) -> Self {
+        self.inner().clone().into()
+    }
 }
 
 impl From<mls_rs::ExtensionList> for ExtensionListFFI {
     fn from(inner: mls_rs::ExtensionList) -> Self {
         Self {
-            _inner: (*inner).clone().into_iter().map(Into::into).collect(),
+            inner: Mutex::new(inner),
         }
     }
 }
 
+impl From<&ExtensionListFFI> for mls_rs::ExtensionList {
+    fn from(extension_list: &ExtensionListFFI) -> Self {
+        extension_list.inner().clone()
+    }
+}
+
+#[uniffi::export]
+impl ExtensionListFFI {
+    #[uniffi::constructor]
+    pub fn new(extensions: Vec<Arc<ExtensionFFI>>) -> Self {
+        let mut inner = mls_rs::ExtensionList::new();
+        for extension in extensions {
+            inner.set(extension.as_ref().into());
+        }
+        inner.into()
+    }
+
+    /// Decode an extension list encoded with [`Self::to_bytes`].
+    #[uniffi::constructor]
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, MlSrsError> {
+        Ok(mls_rs::ExtensionList::mls_decode(&mut &*bytes)?.into())
+    }
+
+    /// The MLS encoding of the list.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MlSrsError> {
+        Ok(self.inner().mls_encode_to_vec()?)
+    }
+
+    /// All extensions in the list.
+    pub fn extensions(&self) -> Vec<Arc<ExtensionFFI>> {
+        self.inner()
+            .iter()
+            .map(|extension| Arc::new(extension.clone().into()))
+            .collect()
+    }
+
+    /// The extension of type `extension_type`, if any.
+    pub fn get(&self, extension_type: u16) -> Option<Arc<ExtensionFFI>> {
+        self.inner()
+            .iter()
+            .find(|extension| extension.extension_type.raw_value() == extension_type)
+            .map(|extension| Arc::new(extension.clone().into()))
+    }
+
+    /// Add `extension`, replacing an extension of the same type.
+    pub fn set(&self, extension: Arc<ExtensionFFI>) {
+        self.inner().set(extension.as_ref().into());
+    }
+
+    /// Remove the extension of type `extension_type`, if any.
+    pub fn remove(&self, extension_type: u16) {
+        self.inner().remove(ExtensionType::new(extension_type));
+    }
+
+    /// Decode the ExternalSenders extension, `None` if the list
+    /// doesn't contain one.
+    ///
+    /// This is used to interpret the extensions passed to
+    /// [`IdentityProviderProtocol::validate_external_sender`](crate::config::IdentityProviderProtocol::validate_external_sender).
+    pub fn external_senders(&self) -> Result<Option<Vec<Arc<SigningIdentityFFI>>>, MlSrsError> {
+        let extensions = mls_rs::ExtensionList::from(self);
+        let external_senders = extensions
+            .get_as::<ExternalSendersExt>()
+            .map_err(|err| err.into_any_error())?;
+        Ok(external_senders.map(|external_senders| {
+            external_senders
+                .allowed_senders
+                .into_iter()
+                .map(|signing_identity| Arc::new(signing_identity.into()))
+                .collect()
+        }))
+    }
+
+    /// Decode the RequiredCapabilities extension, `None` if the list
+    /// doesn't contain one.
+    pub fn required_capabilities(&self) -> Result<Option<RequiredCapabilitiesFFI>, MlSrsError> {
+        let extensions = mls_rs::ExtensionList::from(self);
+        let required_capabilities = extensions
+            .get_as::<RequiredCapabilitiesExt>()
+            .map_err(|err| err.into_any_error())?;
+        Ok(
+            required_capabilities.map(|required_capabilities| RequiredCapabilitiesFFI {
+                extension_types: required_capabilities
+                    .extensions
+                    .iter()
+                    .map(|extension_type| extension_type.raw_value())
+                    .collect(),
+                proposal_types: required_capabilities
+                    .proposals
+                    .iter()
+                    .map(|proposal_type| proposal_type.raw_value())
+                    .collect(),
+                credential_types: required_capabilities
+                    .credentials
+                    .iter()
+                    .map(|credential_type| credential_type.raw_value())
+                    .collect(),
+            }),
+        )
+    }
+
+    /// Decode the ApplicationId extension, `None` if the list doesn't
+    /// contain one.
+    pub fn application_id(&self) -> Result<Option<Vec<u8>>, MlSrsError> {
+        let extensions = mls_rs::ExtensionList::from(self);
+        let application_id = extensions
+            .get_as::<ApplicationIdExt>()
+            .map_err(|err| err.into_any_error())?;
+        Ok(application_id.map(|application_id| application_id.identifier))
+    }
+
+    /// The exported ratchet tree in the RatchetTree extension, `None`
+    /// if the list doesn't contain one.
+    pub fn ratchet_tree(&self) -> Result<Option<Vec<u8>>, MlSrsError> {
+        self.get(ExtensionType::RATCHET_TREE.raw_value())
+            .map(|extension| {
+                ExportedTree::from_bytes(&extension.extension_data)?;
+                Ok(extension.extension_data.clone())
+            })
+            .transpose()
+    }
+}
+
+/// A decoded RequiredCapabilities extension, see
+/// [`required_capabilities_extension`].
+#[derive(Clone, Debug, PartialEq, Eq, uniffi::Record)]
+pub struct RequiredCapabilitiesFFI {
+    pub extension_types: Vec<u16>,
+    pub proposal_types: Vec<u16>,
+    pub credential_types: Vec<u16>,
+}
+
 /// A [`mls_rs::Extension`] wrapper.
 #[derive(uniffi::Object, Debug, Clone)]
 pub struct ExtensionFFI {
@@ -90,6 +250,26 @@ pub struct ExtensionFFI {
     pub extension_data: Vec<u8>,
 }
 
+#[uniffi::export]
+impl ExtensionFFI {
+    #[uniffi::constructor]
+    pub fn new(extension_type_raw: u16, extension_data: Vec<u8>) -> Self {
+        Self {
+            extension_type_raw,
+            extension_data,
+        }
+    }
+}
+
+impl From<&ExtensionFFI> for mls_rs::Extension {
+    fn from(extension: &ExtensionFFI) -> Self {
+        mls_rs::Extension::new(
+            extension.extension_type_raw.into(),
+            extension.extension_data.clone(),
+        )
+    }
+}
+
 impl From<mls_rs::Extension> for ExtensionFFI {
     fn from(
         mls_rs::Extension {
@@ -104,3 +284,73 @@ impl From<mls_rs::Extension> for ExtensionFFI {
         }
     }
 }
+
+/// Create a RequiredCapabilities extension for the group context
+/// extensions of a new group, see
+/// [`ClientFFI::create_group_with_extensions`](crate::client::ClientFFI::create_group_with_extensions).
+///
+/// Members which don't support all listed extension, proposal and
+/// credential types can't join the group, adding them fails with
+/// [`MlSrsError::MissingRequiredCapability`].
+#[uniffi::export]
+pub fn required_capabilities_extension(
+    extension_types: Vec<u16>,
+    proposal_types: Vec<u16>,
+    credential_types: Vec<u16>,
+) -> Result<Arc<ExtensionFFI>, MlSrsError> {
+    let extension = RequiredCapabilitiesExt {
+        extensions: extension_types
+            .into_iter()
+            .map(ExtensionType::new)
+            .collect(),
+        proposals: proposal_types.into_iter().map(ProposalType::new).collect(),
+        credentials: credential_types
+            .into_iter()
+            .map(CredentialType::new)
+            .collect(),
+    }
+    .into_extension()
+    .map_err(|err| err.into_any_error())?;
+    Ok(Arc::new(extension.into()))
+}
+
+/// Create an ExternalSenders extension for the group context
+/// extensions of a new group, allowing `senders` to send proposals
+/// to the group.
+#[uniffi::export]
+pub fn external_senders_extension(
+    senders: Vec<Arc<SigningIdentityFFI>>,
+) -> Result<Arc<ExtensionFFI>, MlSrsError> {
+    let extension = ExternalSendersExt::new(
+        senders
+            .iter()
+            .map(|signing_identity| signing_identity.inner.clone())
+            .collect(),
+    )
+    .into_extension()
+    .map_err(|err| err.into_any_error())?;
+    Ok(Arc::new(extension.into()))
+}
+
+/// Create an ApplicationId extension, e.g., for the leaf node
+/// extensions of a member.
+#[uniffi::export]
+pub fn application_id_extension(application_id: Vec<u8>) -> Result<Arc<ExtensionFFI>, MlSrsError> {
+    let extension = ApplicationIdExt::new(application_id)
+        .into_extension()
+        .map_err(|err| err.into_any_error())?;
+    Ok(Arc::new(extension.into()))
+}
+
+/// Create a RatchetTree extension from an exported ratchet tree, e.g.,
+/// for a group info sent out of band.
+///
+/// Fails if `tree` is not a valid ratchet tree encoding.
+#[uniffi::export]
+pub fn ratchet_tree_extension(tree: Vec<u8>) -> Result<Arc<ExtensionFFI>, MlSrsError> {
+    ExportedTree::from_bytes(&tree)?;
+    Ok(Arc::new(ExtensionFFI::new(
+        ExtensionType::RATCHET_TREE.raw_value(),
+        tree,
+    )))
+}
@@ -1,21 +1,53 @@
 use mls_rs::error::IntoAnyError;
-use mls_rs::mls_rs_codec::{MlsDecode, MlsEncode};
-use mls_rs::psk::ExternalPskId;
+use mls_rs::mls_rs_codec::{MlsDecode, MlsEncode, MlsSize};
+use mls_rs::psk::{ExternalPskId, PreSharedKey};
+use mls_rs::storage_provider::in_memory::{
+    InMemoryGroupStateStorage, InMemoryKeyPackageStorage, InMemoryPreSharedKeyStorage,
+};
 use mls_rs_core::{group::EpochRecord, key_package::KeyPackageData};
 
 use std::fmt::Debug;
+use std::sync::Arc;
+use zeroize::{Zeroize, Zeroizing};
+
+#[cfg(not(mls_build_async))]
 use std::sync::Mutex;
+#[cfg(mls_build_async)]
+use tokio::sync::Mutex;
 
 use crate::mls_rs_error::MlSrsError;
 
-#[derive(Clone, Debug, uniffi::Record)]
+/// A [`KeyPackageData`] wrapper.
+///
+/// `init_key_data` and `leaf_node_key_data` are secret keys. Copies
+/// held in Rust are zeroized with [`Zeroize`].
+#[derive(Clone, MlsSize, MlsEncode, MlsDecode, uniffi::Record)]
 pub struct KeyPackageDataFFI {
+    #[mls_codec(with = "mls_rs_codec::byte_vec")]
     pub key_package_bytes: Vec<u8>,
+    #[mls_codec(with = "mls_rs_codec::byte_vec")]
     pub init_key_data: Vec<u8>,
+    #[mls_codec(with = "mls_rs_codec::byte_vec")]
     pub leaf_node_key_data: Vec<u8>,
     pub expiration: u64,
 }
 
+impl Debug for KeyPackageDataFFI {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyPackageDataFFI")
+            .field("key_package_bytes", &self.key_package_bytes)
+            .field("expiration", &self.expiration)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Zeroize for KeyPackageDataFFI {
+    fn zeroize(&mut self) {
+        self.init_key_data.zeroize();
+        self.leaf_node_key_data.zeroize();
+    }
+}
+
 impl From<KeyPackageData> for KeyPackageDataFFI {
     fn from(
         KeyPackageData {
@@ -55,11 +87,18 @@ impl From<KeyPackageDataFFI> for KeyPackageData {
 }
 
 //mirrors mls-rs-core::psk::PreSharedKeyStorage
-#[maybe_async::must_be_sync]
-#[uniffi::export(with_foreign)]
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
 pub trait PreSharedKeyStorageProtocol: Send + Sync + Debug {
-    fn get(&self, id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError>;
-    //insert and clear externally
+    async fn get(&self, id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError>;
+
+    /// Store `psk` under the MLS encoded `id`, see [`mls_encode`].
+    async fn insert(&self, id: Vec<u8>, psk: Vec<u8>) -> Result<(), MlSrsError>;
+
+    /// Delete the pre-shared key stored under the MLS encoded `id`.
+    async fn delete(&self, id: Vec<u8>) -> Result<(), MlSrsError>;
 }
 
 //ExternalPskId's interior bare data is private, so we store it as MLS encoded
@@ -71,6 +110,65 @@ pub fn mls_encode(external_psk_id: Vec<u8>) -> Result<Vec<u8>, MlSrsError> {
         .map_err(Into::into)
 }
 
+/// The id of an external pre-shared key.
+///
+/// Holds the raw id and converts it to and from the MLS encoding used
+/// as key by [`PreSharedKeyStorageProtocol`].
+#[derive(Clone, Debug, PartialEq, Eq, uniffi::Object)]
+pub struct ExternalPskIdFFI {
+    raw: Vec<u8>,
+}
+
+impl ExternalPskIdFFI {
+    pub(crate) fn inner(&self) -> ExternalPskId {
+        ExternalPskId::new(self.raw.clone())
+    }
+}
+
+#[uniffi::export]
+impl ExternalPskIdFFI {
+    /// Create an id from its raw bytes.
+    #[uniffi::constructor]
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { raw: bytes }
+    }
+
+    /// Create an id from its MLS encoding, e.g., a key of
+    /// [`PreSharedKeyStorageProtocol`].
+    #[uniffi::constructor]
+    pub fn from_mls_encoded(bytes: Vec<u8>) -> Result<Self, MlSrsError> {
+        // The id is encoded as a variable length byte vector.
+        let raw = Vec::<u8>::mls_decode(&mut &*bytes)?;
+        Ok(Self { raw })
+    }
+
+    /// The raw bytes of the id.
+    pub fn raw(&self) -> Vec<u8> {
+        self.raw.clone()
+    }
+
+    /// The MLS encoding of the id, see [`mls_encode`].
+    pub fn encoded(&self) -> Result<Vec<u8>, MlSrsError> {
+        Ok(self.inner().mls_encode_to_vec()?)
+    }
+}
+
+/// Pre-shared key storage which can be written to.
+pub(crate) trait WritablePreSharedKeyStorage {
+    fn insert(&mut self, id: ExternalPskId, psk: PreSharedKey);
+    fn delete(&mut self, id: &ExternalPskId);
+}
+
+impl WritablePreSharedKeyStorage for InMemoryPreSharedKeyStorage {
+    fn insert(&mut self, id: ExternalPskId, psk: PreSharedKey) {
+        InMemoryPreSharedKeyStorage::insert(self, id, psk)
+    }
+
+    fn delete(&mut self, id: &ExternalPskId) {
+        InMemoryPreSharedKeyStorage::delete(self, id)
+    }
+}
+
 /// Adapt a mls-rs `PreSharedKeyStorage` implementation.
 ///
 /// This is used to adapt a mls-rs `PreSharedKeyStorage` implementation
@@ -84,27 +182,52 @@ impl<S> PreSharedKeyStorageAdapter<S> {
         Self(Mutex::new(psk_storage))
     }
 
+    #[cfg(not(mls_build_async))]
     fn inner(&self) -> std::sync::MutexGuard<'_, S> {
         self.0.lock().unwrap()
     }
+
+    #[cfg(mls_build_async)]
+    async fn inner(&self) -> tokio::sync::MutexGuard<'_, S> {
+        self.0.lock().await
+    }
 }
 
-#[maybe_async::must_be_sync]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 impl<S, Err> PreSharedKeyStorageProtocol for PreSharedKeyStorageAdapter<S>
 where
-    S: mls_rs::PreSharedKeyStorage<Error = Err> + Debug,
+    S: mls_rs::PreSharedKeyStorage<Error = Err> + WritablePreSharedKeyStorage + Debug,
     Err: IntoAnyError,
 {
-    fn get(&self, id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError> {
+    async fn get(&self, id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError> {
         self.inner()
+            .await
             .get(&ExternalPskId::mls_decode(&mut &*id)?)
+            .await
             .map(|option| option.map(|result| result.raw_value().to_vec()))
             .map_err(|err| err.into_any_error().into())
     }
+
+    async fn insert(&self, id: Vec<u8>, psk: Vec<u8>) -> Result<(), MlSrsError> {
+        self.inner()
+            .await
+            .insert(ExternalPskId::mls_decode(&mut &*id)?, psk.into());
+        Ok(())
+    }
+
+    async fn delete(&self, id: Vec<u8>) -> Result<(), MlSrsError> {
+        self.inner()
+            .await
+            .delete(&ExternalPskId::mls_decode(&mut &*id)?);
+        Ok(())
+    }
 }
 
-#[maybe_async::must_be_sync]
-#[uniffi::export(with_foreign)]
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
 pub trait KeyPackageStorageProtocol: Send + Sync + Debug {
     /// Delete [`KeyPackageData`] referenced by `id`.
     ///
@@ -127,6 +250,20 @@ pub trait KeyPackageStorageProtocol: Send + Sync + Debug {
     /// `None` should be returned in the event that no key packages are found
     /// that match `id`.
     async fn get(&self, id: Vec<u8>) -> Result<Option<KeyPackageDataFFI>, MlSrsError>;
+
+    /// List the `id`s of all stored [`KeyPackageData`].
+    async fn key_package_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError>;
+}
+
+/// Key package storage which can list the stored key packages.
+pub(crate) trait KeyPackageIds {
+    fn key_package_ids(&self) -> Vec<Vec<u8>>;
+}
+
+impl KeyPackageIds for InMemoryKeyPackageStorage {
+    fn key_package_ids(&self) -> Vec<Vec<u8>> {
+        InMemoryKeyPackageStorage::key_package_ids(self)
+    }
 }
 
 /// Adapt a mls-rs `KeyPackageStorage` implementation.
@@ -142,15 +279,22 @@ impl<S> KeyPackageStorageAdapter<S> {
         Self(Mutex::new(keypackage_storage))
     }
 
+    #[cfg(not(mls_build_async))]
     fn inner(&self) -> std::sync::MutexGuard<'_, S> {
         self.0.lock().unwrap()
     }
+
+    #[cfg(mls_build_async)]
+    async fn inner(&self) -> tokio::sync::MutexGuard<'_, S> {
+        self.0.lock().await
+    }
 }
 
-#[maybe_async::must_be_sync]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 impl<S, Err> KeyPackageStorageProtocol for KeyPackageStorageAdapter<S>
 where
-    S: mls_rs::KeyPackageStorage<Error = Err> + Debug,
+    S: mls_rs::KeyPackageStorage<Error = Err> + KeyPackageIds + Debug,
     Err: IntoAnyError,
 {
     async fn delete(&self, id: Vec<u8>) -> Result<(), MlSrsError> {
@@ -173,10 +317,14 @@ where
         self.inner()
             .await
             .get(&id)
-            .map(|option| option.map(|result| result.into()))
             .await
+            .map(|option| option.map(|result| result.into()))
             .map_err(|err| err.into_any_error().into())
     }
+
+    async fn key_package_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        Ok(self.inner().await.key_package_ids())
+    }
 }
 
 //MARK: Group Storage
@@ -186,10 +334,13 @@ where
 // generate a Python module which must be in a subdirectory of the
 // directory with test scripts which is not supported by the script we
 // use.
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, uniffi::Record)]
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, MlsSize, MlsEncode, MlsDecode, uniffi::Record,
+)]
 pub struct EpochRecordFFI {
     /// A unique epoch identifier within a particular group.
     pub id: u64,
+    #[mls_codec(with = "mls_rs_codec::byte_vec")]
     pub data: Vec<u8>,
 }
 
@@ -205,25 +356,197 @@ impl From<EpochRecordFFI> for mls_rs_core::group::EpochRecord {
     }
 }
 
+/// A group state together with its stored epoch records.
+///
+/// This is the encoding used by
+/// [`GroupFFI::to_snapshot_bytes`](crate::group::GroupFFI::to_snapshot_bytes).
+#[derive(Clone, Debug, MlsSize, MlsEncode, MlsDecode)]
+pub(crate) struct GroupSnapshot {
+    #[mls_codec(with = "mls_rs_codec::byte_vec")]
+    pub group_id: Vec<u8>,
+    #[mls_codec(with = "mls_rs_codec::byte_vec")]
+    pub state: Vec<u8>,
+    pub epochs: Vec<EpochRecordFFI>,
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl GroupSnapshot {
+    /// Read the group `group_id` from `storage`.
+    pub async fn read(
+        storage: &dyn GroupStateStorageProtocol,
+        group_id: Vec<u8>,
+    ) -> Result<Self, MlSrsError> {
+        let state = storage
+            .state(group_id.clone())
+            .await?
+            .ok_or(mls_rs::error::MlsError::GroupNotFound)?;
+
+        let mut epochs = Vec::new();
+        if let Some(max_epoch_id) = storage.max_epoch_id(group_id.clone()).await? {
+            for id in (0..=max_epoch_id).rev() {
+                match storage.epoch(group_id.clone(), id).await? {
+                    Some(data) => epochs.push(EpochRecordFFI { id, data }),
+                    None => break,
+                }
+            }
+        }
+        epochs.reverse();
+
+        Ok(Self {
+            group_id,
+            state,
+            epochs,
+        })
+    }
+
+    /// Write the group into `storage`.
+    pub async fn write(self, storage: &dyn GroupStateStorageProtocol) -> Result<(), MlSrsError> {
+        storage
+            .write(self.group_id, self.state, self.epochs, Vec::new(), None)
+            .await
+    }
+}
+
+/// Serialize all groups in `storage`.
+///
+/// This works with any storage, including the in-memory default, and
+/// is meant for snapshotting client state in tests. Restore the
+/// groups with [`import_group_states`].
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+pub async fn export_group_states(
+    storage: Arc<dyn GroupStateStorageProtocol>,
+) -> Result<Vec<u8>, MlSrsError> {
+    let mut snapshots = Vec::new();
+    for group_id in storage.group_ids().await? {
+        snapshots.push(GroupSnapshot::read(&*storage, group_id).await?);
+    }
+    Ok(snapshots.mls_encode_to_vec()?)
+}
+
+/// Write groups serialized with [`export_group_states`] into
+/// `storage`.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+pub async fn import_group_states(
+    storage: Arc<dyn GroupStateStorageProtocol>,
+    bytes: Vec<u8>,
+) -> Result<(), MlSrsError> {
+    for snapshot in Vec::<GroupSnapshot>::mls_decode(&mut &*bytes)? {
+        snapshot.write(&*storage).await?;
+    }
+    Ok(())
+}
+
+/// A key package together with its id, see [`export_key_packages`].
+#[derive(Clone, Debug, MlsSize, MlsEncode, MlsDecode)]
+struct StoredKeyPackage {
+    #[mls_codec(with = "mls_rs_codec::byte_vec")]
+    id: Vec<u8>,
+    data: KeyPackageDataFFI,
+}
+
+/// Serialize all key packages in `storage`.
+///
+/// The export contains secret keys. Restore the key packages with
+/// [`import_key_packages`].
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+pub async fn export_key_packages(
+    storage: Arc<dyn KeyPackageStorageProtocol>,
+) -> Result<Vec<u8>, MlSrsError> {
+    let mut key_packages = Vec::new();
+    for id in storage.key_package_ids().await? {
+        if let Some(data) = storage.get(id.clone()).await? {
+            key_packages.push(StoredKeyPackage { id, data });
+        }
+    }
+    let bytes = key_packages.mls_encode_to_vec();
+    for key_package in &mut key_packages {
+        key_package.data.zeroize();
+    }
+    Ok(bytes?)
+}
+
+/// Write key packages serialized with [`export_key_packages`] into
+/// `storage`.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+pub async fn import_key_packages(
+    storage: Arc<dyn KeyPackageStorageProtocol>,
+    bytes: Vec<u8>,
+) -> Result<(), MlSrsError> {
+    let bytes = Zeroizing::new(bytes);
+    for StoredKeyPackage { id, data } in Vec::<StoredKeyPackage>::mls_decode(&mut &**bytes)? {
+        storage.insert(id, data).await?;
+    }
+    Ok(())
+}
+
 // When building for async, uniffi::export has to be applied _before_ maybe-async's injection of
 // the async trait so that uniffi::export sees the definition before async_trait is expanded. When
 // building for sync, the order has to be the opposite so that uniffi::export sees the sync
 // definition of the trait.
-#[maybe_async::must_be_sync]
-#[uniffi::export(with_foreign)]
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
 pub trait GroupStateStorageProtocol: Send + Sync + Debug {
     async fn state(&self, group_id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError>;
     async fn epoch(&self, group_id: Vec<u8>, epoch_id: u64) -> Result<Option<Vec<u8>>, MlSrsError>;
 
+    /// Store the group state, epoch inserts and updates of `group_id`
+    /// and delete its epoch records with an id lower than
+    /// `prune_before`, if set.
+    ///
+    /// This should be applied atomically, so a failure never leaves
+    /// the group state and its epochs inconsistent. Pruning is
+    /// requested when
+    /// [`ClientConfigFFI::max_stored_epochs`](crate::config::ClientConfigFFI::max_stored_epochs)
+    /// is set.
     async fn write(
         &self,
         group_id: Vec<u8>,
         group_state: Vec<u8>,
         epoch_inserts: Vec<EpochRecordFFI>,
         epoch_updates: Vec<EpochRecordFFI>,
+        prune_before: Option<u64>,
     ) -> Result<(), MlSrsError>;
 
     async fn max_epoch_id(&self, group_id: Vec<u8>) -> Result<Option<u64>, MlSrsError>;
+
+    /// Delete the group state and all epoch records of `group_id`.
+    async fn delete_group(&self, group_id: Vec<u8>) -> Result<(), MlSrsError>;
+
+    /// List the ids of all stored groups.
+    async fn group_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError>;
+}
+
+/// Group state storage which can list and delete stored groups.
+pub(crate) trait GroupStateStorageExt {
+    fn group_ids(&self) -> Vec<Vec<u8>>;
+    fn delete_group(&mut self, group_id: &[u8]);
+    fn prune_epochs(&mut self, group_id: &[u8], before_epoch_id: u64);
+}
+
+impl GroupStateStorageExt for InMemoryGroupStateStorage {
+    fn group_ids(&self) -> Vec<Vec<u8>> {
+        self.stored_groups()
+    }
+
+    fn delete_group(&mut self, group_id: &[u8]) {
+        InMemoryGroupStateStorage::delete_group(self, group_id)
+    }
+
+    fn prune_epochs(&mut self, _group_id: &[u8], _before_epoch_id: u64) {
+        // Epochs beyond the configured max_epoch_retention are
+        // already dropped on write and can't be removed individually.
+    }
 }
 
 /// Adapt a mls-rs `GroupStateStorage` implementation.
@@ -239,15 +562,22 @@ impl<S> GroupStateStorageAdapter<S> {
         Self(Mutex::new(group_state_storage))
     }
 
+    #[cfg(not(mls_build_async))]
     fn inner(&self) -> std::sync::MutexGuard<'_, S> {
         self.0.lock().unwrap()
     }
+
+    #[cfg(mls_build_async)]
+    async fn inner(&self) -> tokio::sync::MutexGuard<'_, S> {
+        self.0.lock().await
+    }
 }
 
-#[maybe_async::must_be_sync]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 impl<S, Err> GroupStateStorageProtocol for GroupStateStorageAdapter<S>
 where
-    S: mls_rs::GroupStateStorage<Error = Err> + Debug,
+    S: mls_rs::GroupStateStorage<Error = Err> + GroupStateStorageExt + Debug,
     Err: IntoAnyError,
 {
     async fn state(&self, group_id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError> {
@@ -272,16 +602,25 @@ where
         data: Vec<u8>,
         epoch_inserts: Vec<EpochRecordFFI>,
         epoch_updates: Vec<EpochRecordFFI>,
+        prune_before: Option<u64>,
     ) -> Result<(), MlSrsError> {
-        self.inner()
-            .await
+        let mut inner = self.inner().await;
+        inner
             .write(
-                mls_rs_core::group::GroupState { id, data }.into(),
+                mls_rs_core::group::GroupState {
+                    id: id.clone(),
+                    data,
+                }
+                .into(),
                 epoch_inserts.into_iter().map(Into::into).collect(),
                 epoch_updates.into_iter().map(Into::into).collect(),
             )
             .await
-            .map_err(|err| err.into_any_error().into())
+            .map_err(|err| err.into_any_error())?;
+        if let Some(before_epoch_id) = prune_before {
+            inner.prune_epochs(&id, before_epoch_id);
+        }
+        Ok(())
     }
 
     async fn max_epoch_id(&self, group_id: Vec<u8>) -> Result<Option<u64>, MlSrsError> {
@@ -291,4 +630,13 @@ where
             .await
             .map_err(|err| err.into_any_error().into())
     }
+
+    async fn delete_group(&self, group_id: Vec<u8>) -> Result<(), MlSrsError> {
+        self.inner().await.delete_group(&group_id);
+        Ok(())
+    }
+
+    async fn group_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        Ok(self.inner().await.group_ids())
+    }
 }
@@ -0,0 +1,75 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Receives a [`MetricFFI`] for operations of a client, see
+/// [`ClientConfigFFI::metrics`](crate::config::ClientConfigFFI::metrics).
+///
+/// This is called synchronously after each operation, so it should
+/// return quickly.
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
+pub trait MetricsProtocol: Send + Sync + Debug {
+    fn on_metric(&self, metric: MetricFFI);
+}
+
+/// A single measurement reported to a [`MetricsProtocol`].
+#[derive(Clone, Debug, uniffi::Enum)]
+pub enum MetricFFI {
+    /// A commit was built.
+    CommitBuilt { duration_micros: u64, success: bool },
+    /// An incoming message was processed.
+    MessageProcessed { duration_micros: u64, success: bool },
+    /// A group state was written to storage. The size includes the
+    /// group state and all epoch records.
+    StorageWrite { bytes: u64 },
+    /// A signature or HPKE operation finished.
+    CryptoOperation {
+        operation: CryptoOperationFFI,
+        duration_micros: u64,
+        success: bool,
+    },
+    /// A tracing span closed, see
+    /// [`SpanMetricsLayer`](crate::span_logger::SpanMetricsLayer).
+    Span { name: String, duration_micros: u64 },
+}
+
+/// The crypto operations timed by [`MetricFFI::CryptoOperation`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum CryptoOperationFFI {
+    Sign,
+    Verify,
+    HpkeSeal,
+    HpkeOpen,
+}
+
+/// An optional [`MetricsProtocol`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Metrics(Option<Arc<dyn MetricsProtocol>>);
+
+impl Metrics {
+    pub fn new(inner: Option<Arc<dyn MetricsProtocol>>) -> Self {
+        Self(inner)
+    }
+
+    pub fn report(&self, metric: MetricFFI) {
+        if let Some(inner) = &self.0 {
+            inner.on_metric(metric);
+        }
+    }
+
+    /// Report the time since `start` and whether `result` is ok.
+    pub fn report_timed<T, E>(
+        &self,
+        start: Instant,
+        result: &Result<T, E>,
+        metric: impl FnOnce(u64, bool) -> MetricFFI,
+    ) {
+        if let Some(inner) = &self.0 {
+            let duration_micros = start.elapsed().as_micros() as u64;
+            inner.on_metric(metric(duration_micros, result.is_ok()));
+        }
+    }
+}
@@ -0,0 +1,710 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use mls_rs::client_builder::PaddingMode;
+use mls_rs::error::IntoAnyError;
+use mls_rs::group::proposal::{BorrowedProposal, Proposal};
+use mls_rs::group::Roster;
+use mls_rs::mls_rules::ProposalInfo;
+use mls_rs::mls_rules::{
+    CommitDirection, CommitOptions, CommitSource, DefaultMlsRules, EncryptionOptions,
+    ProposalBundle, ProposalSource,
+};
+use mls_rs::MlsRules;
+use mls_rs_core::group::GroupContext;
+
+use crate::config::group_context::GroupContextFFI;
+use crate::config::SigningIdentityFFI;
+use crate::message::{ProposalFFI, SenderFFI};
+use crate::mls_rs_error::MlSrsError;
+
+/// The [`mls_rs::MlsRules`] used by [`UniFFIConfig`](crate::config::UniFFIConfig).
+///
+/// Wraps [`DefaultMlsRules`] and additionally lets a group restrict
+/// which cached proposals are included in its next commit.
+#[derive(Clone, Debug)]
+pub struct UniFFIMlsRules {
+    inner: DefaultMlsRules,
+    max_group_size: Option<u32>,
+    max_authenticated_data_size: Option<u32>,
+    max_past_epochs: Option<u32>,
+    proposal_filters: Arc<Mutex<HashMap<Vec<u8>, ProposalFilter>>>,
+    foreign_rules: Option<Arc<dyn MlsRulesProtocol>>,
+    proposal_validator: Option<Arc<dyn ProposalValidatorProtocol>>,
+    custom_proposal_policies: Vec<CustomProposalPolicyFFI>,
+    reject_unknown_custom_proposals: bool,
+}
+
+/// How a custom proposal type must be included in commits.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct CustomProposalPolicyFFI {
+    pub proposal_type: u16,
+    pub inclusion: ProposalInclusionFFI,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum ProposalInclusionFFI {
+    /// The proposal must be sent inside the commit.
+    ByValue,
+    /// The proposal must be sent separately and committed by
+    /// reference.
+    ByReference,
+    /// Either way.
+    Any,
+}
+
+impl ProposalInclusionFFI {
+    fn allows(&self, source: &ProposalSource) -> bool {
+        let by_reference = matches!(source, ProposalSource::ByReference(_));
+        match self {
+            Self::ByValue => !by_reference,
+            Self::ByReference => by_reference,
+            Self::Any => true,
+        }
+    }
+}
+
+/// Application policy applied on top of the rules configured in
+/// [`ClientConfigFFI`](crate::config::ClientConfigFFI).
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
+pub trait MlsRulesProtocol: Send + Sync + Debug {
+    /// Select the proposals of a commit we send or receive.
+    ///
+    /// Returns the indexes of the proposals to keep. mls-rs orders
+    /// proposals by type, so the order of the indexes is ignored. A
+    /// received commit is rejected by returning an error.
+    async fn filter_proposals(
+        &self,
+        direction: CommitDirectionFFI,
+        group_context: GroupContextFFI,
+        proposals: Vec<RulesProposalFFI>,
+    ) -> Result<Vec<u32>, MlSrsError>;
+
+    /// Adjust the options of a commit we send based on the
+    /// proposals it includes, e.g., to require a path update
+    /// whenever a member is removed.
+    ///
+    /// The group context is the one of the new epoch.
+    fn commit_options(
+        &self,
+        group_context: GroupContextFFI,
+        proposals: Vec<RulesProposalFFI>,
+        options: CommitOptionsFFI,
+    ) -> Result<CommitOptionsFFI, MlSrsError>;
+
+    /// Adjust the options of the messages we send.
+    fn encryption_options(
+        &self,
+        group_context: GroupContextFFI,
+        options: EncryptionOptionsFFI,
+    ) -> Result<EncryptionOptionsFFI, MlSrsError>;
+}
+
+/// Validates the proposals of every commit we send or receive.
+///
+/// This is called with the final list of proposals after all rules
+/// are applied. An error rejects the commit, e.g., to only let admins
+/// remove members.
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
+pub trait ProposalValidatorProtocol: Send + Sync + Debug {
+    async fn validate_proposals(
+        &self,
+        direction: CommitDirectionFFI,
+        committer: CommitSourceFFI,
+        group_context: GroupContextFFI,
+        proposals: Vec<RulesProposalFFI>,
+    ) -> Result<(), MlSrsError>;
+}
+
+/// A [`CommitSource`] wrapper.
+#[derive(Clone, Debug, uniffi::Enum)]
+pub enum CommitSourceFFI {
+    /// A current member of the group.
+    ExistingMember {
+        index: u32,
+        signing_identity: Arc<SigningIdentityFFI>,
+    },
+    /// A new member joining with an external commit.
+    NewMember {
+        signing_identity: Arc<SigningIdentityFFI>,
+    },
+}
+
+impl From<&CommitSource> for CommitSourceFFI {
+    fn from(source: &CommitSource) -> Self {
+        match source {
+            CommitSource::ExistingMember(member) => Self::ExistingMember {
+                index: member.index,
+                signing_identity: Arc::new(member.signing_identity.clone().into()),
+            },
+            CommitSource::NewMember(signing_identity) => Self::NewMember {
+                signing_identity: Arc::new(signing_identity.clone().into()),
+            },
+        }
+    }
+}
+
+/// A [`CommitDirection`] wrapper.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum CommitDirectionFFI {
+    Send,
+    Receive,
+}
+
+impl From<CommitDirection> for CommitDirectionFFI {
+    fn from(direction: CommitDirection) -> Self {
+        match direction {
+            CommitDirection::Send => Self::Send,
+            CommitDirection::Receive => Self::Receive,
+        }
+    }
+}
+
+/// A proposal passed to [`MlsRulesProtocol`] and
+/// [`ProposalValidatorProtocol`].
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct RulesProposalFFI {
+    pub proposal: ProposalFFI,
+    pub sender: SenderFFI,
+    /// Reference of a cached proposal, `None` if the proposal is
+    /// included by value.
+    pub proposal_ref: Option<Vec<u8>>,
+}
+
+/// The proposals of `proposals` as passed to [`MlsRulesProtocol`].
+fn rules_proposals(proposals: &ProposalBundle) -> Result<Vec<RulesProposalFFI>, MlSrsError> {
+    proposals
+        .iter_proposals()
+        .map(|info| {
+            let proposal_ref = match &info.source {
+                ProposalSource::ByReference(reference) => Some(reference.to_vec()),
+                _ => None,
+            };
+            let sender = (&info.sender).into();
+            let proposal = ProposalFFI::try_from(ProposalInfo {
+                proposal: Proposal::from(info.proposal),
+                sender: info.sender,
+                source: info.source,
+            })?;
+            Ok(RulesProposalFFI {
+                proposal,
+                sender,
+                proposal_ref,
+            })
+        })
+        .collect()
+}
+
+/// A [`CommitOptions`] wrapper.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, uniffi::Record)]
+pub struct CommitOptionsFFI {
+    pub path_required: bool,
+    pub ratchet_tree_extension: bool,
+    pub single_welcome_message: bool,
+    pub allow_external_commit: bool,
+}
+
+impl From<&CommitOptions> for CommitOptionsFFI {
+    fn from(options: &CommitOptions) -> Self {
+        Self {
+            path_required: options.path_required,
+            ratchet_tree_extension: options.ratchet_tree_extension,
+            single_welcome_message: options.single_welcome_message,
+            allow_external_commit: options.allow_external_commit,
+        }
+    }
+}
+
+/// A [`EncryptionOptions`] wrapper.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, uniffi::Record)]
+pub struct EncryptionOptionsFFI {
+    pub encrypt_control_messages: bool,
+    pub padding_mode: PaddingModeFFI,
+}
+
+impl TryFrom<&EncryptionOptions> for EncryptionOptionsFFI {
+    type Error = MlSrsError;
+
+    fn try_from(options: &EncryptionOptions) -> Result<Self, Self::Error> {
+        Ok(Self {
+            encrypt_control_messages: options.encrypt_control_messages,
+            padding_mode: options.padding_mode.try_into()?,
+        })
+    }
+}
+
+/// A [`PaddingMode`] wrapper.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum PaddingModeFFI {
+    /// Pad to the next step of a step function, the default.
+    StepFunction,
+    /// Don't pad, e.g., for typing indicators and receipts whose size
+    /// doesn't reveal anything.
+    None,
+}
+
+impl TryFrom<PaddingMode> for PaddingModeFFI {
+    type Error = MlSrsError;
+
+    fn try_from(padding_mode: PaddingMode) -> Result<Self, Self::Error> {
+        match padding_mode {
+            PaddingMode::StepFunction => Ok(PaddingModeFFI::StepFunction),
+            PaddingMode::None => Ok(PaddingModeFFI::None),
+            // mls-rs may add padding modes the bindings don't know.
+            _ => Err(MlSrsError::NotImplemented),
+        }
+    }
+}
+
+impl From<PaddingModeFFI> for PaddingMode {
+    fn from(padding_mode: PaddingModeFFI) -> Self {
+        match padding_mode {
+            PaddingModeFFI::StepFunction => PaddingMode::StepFunction,
+            PaddingModeFFI::None => PaddingMode::None,
+        }
+    }
+}
+
+/// Per-group restrictions on the cached proposals we commit.
+#[derive(Debug, Default)]
+struct ProposalFilter {
+    /// Epoch the excluded references belong to.
+    epoch: u64,
+    /// If set, only these proposal references are committed.
+    selected: Option<Vec<Vec<u8>>>,
+    /// Proposal references we do not commit in `epoch`.
+    excluded: Vec<Vec<u8>>,
+}
+
+impl ProposalFilter {
+    fn is_empty(&self) -> bool {
+        self.selected.is_none() && self.excluded.is_empty()
+    }
+
+    fn allows(&self, epoch: u64, reference: &[u8]) -> bool {
+        let selected = self
+            .selected
+            .as_ref()
+            .map_or(true, |selected| selected.iter().any(|r| r == reference));
+        let excluded = self.epoch == epoch && self.excluded.iter().any(|r| r == reference);
+        selected && !excluded
+    }
+}
+
+impl UniFFIMlsRules {
+    pub fn new(inner: DefaultMlsRules) -> Self {
+        Self {
+            inner,
+            max_group_size: None,
+            max_authenticated_data_size: None,
+            max_past_epochs: None,
+            proposal_filters: Default::default(),
+            foreign_rules: None,
+            proposal_validator: None,
+            custom_proposal_policies: Vec::new(),
+            reject_unknown_custom_proposals: false,
+        }
+    }
+
+    /// Enforce how custom proposals are included in commits.
+    ///
+    /// If `reject_unknown` is set, custom proposals without a policy
+    /// are not allowed either.
+    pub fn with_custom_proposal_policies(
+        self,
+        custom_proposal_policies: Vec<CustomProposalPolicyFFI>,
+        reject_unknown: bool,
+    ) -> Self {
+        Self {
+            custom_proposal_policies,
+            reject_unknown_custom_proposals: reject_unknown,
+            ..self
+        }
+    }
+
+    /// Apply [`Self::with_custom_proposal_policies`]: disallowed
+    /// custom proposals are left out of commits we send and reject
+    /// commits we receive.
+    fn check_custom_proposals(
+        &self,
+        direction: CommitDirection,
+        proposals: &mut ProposalBundle,
+    ) -> Result<(), MlSrsError> {
+        proposals.retain(|info| {
+            let BorrowedProposal::Custom(custom) = &info.proposal else {
+                return Ok(true);
+            };
+            let proposal_type = custom.proposal_type().raw_value();
+            let allowed = match self
+                .custom_proposal_policies
+                .iter()
+                .find(|policy| policy.proposal_type == proposal_type)
+            {
+                Some(policy) => policy.inclusion.allows(&info.source),
+                None => !self.reject_unknown_custom_proposals,
+            };
+            match direction {
+                _ if allowed => Ok(true),
+                CommitDirection::Send => Ok(false),
+                CommitDirection::Receive => {
+                    Err(MlSrsError::CustomProposalNotAllowed { proposal_type })
+                }
+            }
+        })
+    }
+
+    /// Let `proposal_validator` reject commits.
+    pub fn with_proposal_validator(
+        self,
+        proposal_validator: Option<Arc<dyn ProposalValidatorProtocol>>,
+    ) -> Self {
+        Self {
+            proposal_validator,
+            ..self
+        }
+    }
+
+    /// Apply the application policy in `foreign_rules` after our own
+    /// rules.
+    pub fn with_foreign_rules(self, foreign_rules: Option<Arc<dyn MlsRulesProtocol>>) -> Self {
+        Self {
+            foreign_rules,
+            ..self
+        }
+    }
+
+    /// Refuse to create commits resulting in more than
+    /// `max_group_size` members.
+    pub fn with_max_group_size(self, max_group_size: Option<u32>) -> Self {
+        Self {
+            max_group_size,
+            ..self
+        }
+    }
+
+    /// Refuse to send messages with more than
+    /// `max_authenticated_data_size` bytes of authenticated data.
+    pub fn with_max_authenticated_data_size(
+        self,
+        max_authenticated_data_size: Option<u32>,
+    ) -> Self {
+        Self {
+            max_authenticated_data_size,
+            ..self
+        }
+    }
+
+    /// Check the authenticated data of a message we send against
+    /// the configured maximum size.
+    pub(crate) fn check_authenticated_data(
+        &self,
+        authenticated_data: &[u8],
+    ) -> Result<(), MlSrsError> {
+        match self.max_authenticated_data_size {
+            Some(max_size) if authenticated_data.len() > max_size as usize => {
+                Err(MlSrsError::AuthenticatedDataTooLarge {
+                    size: authenticated_data.len() as u64,
+                    max_size,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Refuse to process messages from more than `max_past_epochs`
+    /// epochs before the current one.
+    pub fn with_max_past_epochs(self, max_past_epochs: Option<u32>) -> Self {
+        Self {
+            max_past_epochs,
+            ..self
+        }
+    }
+
+    /// Check the epoch of a received message against the configured
+    /// maximum number of past epochs.
+    pub(crate) fn check_message_epoch(
+        &self,
+        message_epoch: Option<u64>,
+        group_epoch: u64,
+    ) -> Result<(), MlSrsError> {
+        match (self.max_past_epochs, message_epoch) {
+            (Some(max_past_epochs), Some(message_epoch))
+                if message_epoch + u64::from(max_past_epochs) < group_epoch =>
+            {
+                Err(MlSrsError::WrongEpoch {
+                    message_epoch: Some(message_epoch),
+                    group_epoch: Some(group_epoch),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn proposal_filters(&self) -> std::sync::MutexGuard<'_, HashMap<Vec<u8>, ProposalFilter>> {
+        self.proposal_filters.lock().unwrap()
+    }
+
+    /// Only commit the cached proposals in `refs` for `group_id`
+    /// until the returned guard is dropped.
+    pub(crate) fn select_proposals(
+        &self,
+        group_id: &[u8],
+        refs: Vec<Vec<u8>>,
+    ) -> ProposalSelection<'_> {
+        self.proposal_filters()
+            .entry(group_id.to_vec())
+            .or_default()
+            .selected = Some(refs);
+        ProposalSelection {
+            rules: self,
+            group_id: group_id.to_vec(),
+        }
+    }
+
+    /// Leave the cached proposal `reference` out of the commits we
+    /// send in `epoch` of `group_id`.
+    ///
+    /// The exclusion is forgotten with the next commit we receive or
+    /// the first commit we build in a later epoch, see
+    /// [`Self::forget_stale_exclusions`].
+    pub(crate) fn exclude_proposal(&self, group_id: &[u8], epoch: u64, reference: Vec<u8>) {
+        let mut filters = self.proposal_filters();
+        let filter = filters.entry(group_id.to_vec()).or_default();
+        if filter.epoch != epoch {
+            filter.epoch = epoch;
+            filter.excluded.clear();
+        }
+        filter.excluded.push(reference);
+    }
+
+    /// Drop the exclusions of `group_id` once they no longer apply,
+    /// i.e., when a commit moves the group out of their epoch.
+    fn forget_stale_exclusions(&self, direction: CommitDirection, context: &GroupContext) {
+        let mut filters = self.proposal_filters();
+        if let Some(filter) = filters.get_mut(&context.group_id) {
+            if matches!(direction, CommitDirection::Receive) || filter.epoch != context.epoch {
+                filter.excluded.clear();
+            }
+            if filter.is_empty() {
+                filters.remove(&context.group_id);
+            }
+        }
+    }
+}
+
+#[cfg(not(mls_build_async))]
+thread_local! {
+    /// Padding of the messages encrypted by [`with_padding`].
+    static PADDING_OVERRIDE: std::cell::Cell<Option<PaddingModeFFI>> =
+        const { std::cell::Cell::new(None) };
+}
+
+#[cfg(mls_build_async)]
+tokio::task_local! {
+    /// Padding of the messages encrypted by [`with_padding`].
+    static PADDING_OVERRIDE: Option<PaddingModeFFI>;
+}
+
+/// Call `encrypt` with `padding` overriding the padding of the
+/// messages it encrypts.
+///
+/// mls-rs only asks the rules for the padding, so the override is
+/// kept with the call instead of with the group.
+#[cfg(not(mls_build_async))]
+pub(crate) fn with_padding<T>(padding: Option<PaddingModeFFI>, encrypt: impl FnOnce() -> T) -> T {
+    struct Restore(Option<PaddingModeFFI>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            PADDING_OVERRIDE.with(|cell| cell.set(self.0));
+        }
+    }
+
+    let _restore = Restore(PADDING_OVERRIDE.with(|cell| cell.replace(padding)));
+    encrypt()
+}
+
+/// Await `encrypt` with `padding` overriding the padding of the
+/// messages it encrypts.
+///
+/// mls-rs only asks the rules for the padding, so the override is
+/// kept with the call instead of with the group.
+#[cfg(mls_build_async)]
+pub(crate) async fn with_padding<F: std::future::Future>(
+    padding: Option<PaddingModeFFI>,
+    encrypt: F,
+) -> F::Output {
+    PADDING_OVERRIDE.scope(padding, encrypt).await
+}
+
+/// The padding set by the enclosing [`with_padding`], if any.
+#[cfg(not(mls_build_async))]
+fn padding_override() -> Option<PaddingModeFFI> {
+    PADDING_OVERRIDE.with(|cell| cell.get())
+}
+
+/// The padding set by the enclosing [`with_padding`], if any.
+#[cfg(mls_build_async)]
+fn padding_override() -> Option<PaddingModeFFI> {
+    PADDING_OVERRIDE.try_with(|padding| *padding).ok().flatten()
+}
+
+/// Clears a proposal selection made with
+/// [`UniFFIMlsRules::select_proposals`] when dropped.
+pub(crate) struct ProposalSelection<'a> {
+    rules: &'a UniFFIMlsRules,
+    group_id: Vec<u8>,
+}
+
+impl Drop for ProposalSelection<'_> {
+    fn drop(&mut self) {
+        let mut filters = self.rules.proposal_filters();
+        if let Some(filter) = filters.get_mut(&self.group_id) {
+            filter.selected = None;
+            if filter.is_empty() {
+                filters.remove(&self.group_id);
+            }
+        }
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl MlsRules for UniFFIMlsRules {
+    type Error = MlSrsError;
+
+    async fn filter_proposals(
+        &self,
+        direction: CommitDirection,
+        source: CommitSource,
+        current_roster: &Roster,
+        current_context: &GroupContext,
+        mut proposals: ProposalBundle,
+    ) -> Result<ProposalBundle, Self::Error> {
+        self.forget_stale_exclusions(direction, current_context);
+        if matches!(direction, CommitDirection::Send) {
+            if let Some(filter) = self.proposal_filters().get(&current_context.group_id) {
+                proposals.retain(|proposal| {
+                    Ok::<_, MlSrsError>(match &proposal.source {
+                        ProposalSource::ByReference(reference) => {
+                            filter.allows(current_context.epoch, reference)
+                        }
+                        _ => true,
+                    })
+                })?;
+            }
+        }
+
+        self.check_custom_proposals(direction, &mut proposals)?;
+
+        let committer = CommitSourceFFI::from(&source);
+        let mut proposals = self
+            .inner
+            .filter_proposals(
+                direction,
+                source,
+                current_roster,
+                current_context,
+                proposals,
+            )
+            .await
+            .map_err(|err| err.into_any_error())?;
+
+        if let Some(foreign_rules) = &self.foreign_rules {
+            let keep = foreign_rules
+                .filter_proposals(
+                    direction.into(),
+                    current_context.clone().try_into()?,
+                    rules_proposals(&proposals)?,
+                )
+                .await?;
+
+            // retain visits the proposals in the same order as
+            // iter_proposals.
+            let mut index = 0;
+            proposals.retain(|_| {
+                let retained = keep.contains(&index);
+                index += 1;
+                Ok::<_, MlSrsError>(retained)
+            })?;
+        }
+
+        if let Some(proposal_validator) = &self.proposal_validator {
+            proposal_validator
+                .validate_proposals(
+                    direction.into(),
+                    committer,
+                    current_context.clone().try_into()?,
+                    rules_proposals(&proposals)?,
+                )
+                .await?;
+        }
+
+        Ok(proposals)
+    }
+
+    fn commit_options(
+        &self,
+        new_roster: &Roster,
+        new_context: &GroupContext,
+        proposals: &ProposalBundle,
+    ) -> Result<CommitOptions, Self::Error> {
+        if let Some(max_group_size) = self.max_group_size {
+            if new_roster.members_iter().count() > max_group_size as usize {
+                return Err(MlSrsError::GroupSizeLimitExceeded { max_group_size });
+            }
+        }
+
+        let mut options = self
+            .inner
+            .commit_options(new_roster, new_context, proposals)
+            .map_err(|err| err.into_any_error())?;
+
+        if let Some(foreign_rules) = &self.foreign_rules {
+            let adjusted = foreign_rules.commit_options(
+                new_context.clone().try_into()?,
+                rules_proposals(proposals)?,
+                (&options).into(),
+            )?;
+            options = options
+                .with_path_required(adjusted.path_required)
+                .with_ratchet_tree_extension(adjusted.ratchet_tree_extension)
+                .with_single_welcome_message(adjusted.single_welcome_message)
+                .with_allow_external_commit(adjusted.allow_external_commit);
+        }
+
+        Ok(options)
+    }
+
+    fn encryption_options(
+        &self,
+        current_roster: &Roster,
+        current_context: &GroupContext,
+    ) -> Result<EncryptionOptions, Self::Error> {
+        let mut options = self
+            .inner
+            .encryption_options(current_roster, current_context)
+            .map_err(|err| err.into_any_error())?;
+
+        if let Some(foreign_rules) = &self.foreign_rules {
+            let adjusted = foreign_rules
+                .encryption_options(current_context.clone().try_into()?, (&options).try_into()?)?;
+            options = EncryptionOptions::new(
+                adjusted.encrypt_control_messages,
+                adjusted.padding_mode.into(),
+            );
+        }
+
+        if let Some(padding_mode) = padding_override() {
+            options.padding_mode = padding_mode.into();
+        }
+        Ok(options)
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use crate::config::group_state::{
+    EpochRecordFFI, GroupStateStorageProtocol, KeyPackageDataFFI, KeyPackageStorageProtocol,
+};
+use crate::mls_rs_error::MlSrsError;
+
+/// Storage which prefixes all group and key package ids with a
+/// namespace, see
+/// [`ClientConfigFFI::storage_namespace`](crate::config::ClientConfigFFI::storage_namespace).
+#[derive(Debug)]
+pub(crate) struct NamespacedStorage<S: ?Sized> {
+    inner: Arc<S>,
+    /// The namespace preceded by its length as a big-endian `u32`, so
+    /// no namespace is a prefix of another one.
+    prefix: Vec<u8>,
+}
+
+impl<S: ?Sized> NamespacedStorage<S> {
+    pub fn new(inner: Arc<S>, namespace: Vec<u8>) -> Self {
+        let prefix = [&(namespace.len() as u32).to_be_bytes()[..], &namespace].concat();
+        Self { inner, prefix }
+    }
+
+    fn key(&self, id: &[u8]) -> Vec<u8> {
+        [self.prefix.as_slice(), id].concat()
+    }
+
+    /// Ids of other namespaces are skipped.
+    fn strip(&self, ids: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        ids.into_iter()
+            .filter_map(|id| id.strip_prefix(self.prefix.as_slice()).map(<[u8]>::to_vec))
+            .collect()
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl GroupStateStorageProtocol for NamespacedStorage<dyn GroupStateStorageProtocol> {
+    async fn state(&self, group_id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError> {
+        self.inner.state(self.key(&group_id)).await
+    }
+
+    async fn epoch(&self, group_id: Vec<u8>, epoch_id: u64) -> Result<Option<Vec<u8>>, MlSrsError> {
+        self.inner.epoch(self.key(&group_id), epoch_id).await
+    }
+
+    async fn write(
+        &self,
+        group_id: Vec<u8>,
+        group_state: Vec<u8>,
+        epoch_inserts: Vec<EpochRecordFFI>,
+        epoch_updates: Vec<EpochRecordFFI>,
+        prune_before: Option<u64>,
+    ) -> Result<(), MlSrsError> {
+        self.inner
+            .write(
+                self.key(&group_id),
+                group_state,
+                epoch_inserts,
+                epoch_updates,
+                prune_before,
+            )
+            .await
+    }
+
+    async fn max_epoch_id(&self, group_id: Vec<u8>) -> Result<Option<u64>, MlSrsError> {
+        self.inner.max_epoch_id(self.key(&group_id)).await
+    }
+
+    async fn delete_group(&self, group_id: Vec<u8>) -> Result<(), MlSrsError> {
+        self.inner.delete_group(self.key(&group_id)).await
+    }
+
+    async fn group_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        Ok(self.strip(self.inner.group_ids().await?))
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl KeyPackageStorageProtocol for NamespacedStorage<dyn KeyPackageStorageProtocol> {
+    async fn delete(&self, id: Vec<u8>) -> Result<(), MlSrsError> {
+        self.inner.delete(self.key(&id)).await
+    }
+
+    async fn insert(&self, id: Vec<u8>, pkg: KeyPackageDataFFI) -> Result<(), MlSrsError> {
+        self.inner.insert(self.key(&id), pkg).await
+    }
+
+    async fn get(&self, id: Vec<u8>) -> Result<Option<KeyPackageDataFFI>, MlSrsError> {
+        self.inner.get(self.key(&id)).await
+    }
+
+    async fn key_package_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        Ok(self.strip(self.inner.key_package_ids().await?))
+    }
+}
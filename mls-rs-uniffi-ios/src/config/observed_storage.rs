@@ -0,0 +1,218 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::config::group_state::{
+    EpochRecordFFI, GroupStateStorageProtocol, KeyPackageDataFFI, KeyPackageStorageProtocol,
+};
+use crate::mls_rs_error::MlSrsError;
+
+/// A storage read or write reported to a [`StorageObserverProtocol`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum StorageOperationFFI {
+    ReadState,
+    ReadEpoch,
+    WriteGroup,
+    ReadKeyPackage,
+    WriteKeyPackage,
+}
+
+/// Timing and size of a single storage operation.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct StorageEventFFI {
+    pub operation: StorageOperationFFI,
+    /// The group the operation belongs to, `None` for key packages.
+    pub group_id: Option<Vec<u8>>,
+    /// Number of bytes read or written. For group writes, this
+    /// includes the group state and all epoch records.
+    pub size: u64,
+    /// Time spent in the wrapped storage.
+    pub duration_micros: u64,
+    /// Whether the wrapped storage returned an error.
+    pub success: bool,
+}
+
+/// Receives a [`StorageEventFFI`] for every read and write of an
+/// observed storage.
+///
+/// This is called synchronously after each operation, so it should
+/// return quickly.
+#[cfg_attr(mls_build_async, uniffi::export(with_foreign))]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(not(mls_build_async), uniffi::export(with_foreign))]
+pub trait StorageObserverProtocol: Send + Sync + Debug {
+    fn on_storage_event(&self, event: StorageEventFFI);
+}
+
+/// Wrap `inner` so its state and epoch reads and writes are reported
+/// to `observer`.
+#[uniffi::export]
+pub fn observed_group_state_storage(
+    inner: Arc<dyn GroupStateStorageProtocol>,
+    observer: Arc<dyn StorageObserverProtocol>,
+) -> Arc<dyn GroupStateStorageProtocol> {
+    Arc::new(ObservedStorage { inner, observer })
+}
+
+/// Wrap `inner` so its key package reads and writes are reported to
+/// `observer`.
+#[uniffi::export]
+pub fn observed_key_package_storage(
+    inner: Arc<dyn KeyPackageStorageProtocol>,
+    observer: Arc<dyn StorageObserverProtocol>,
+) -> Arc<dyn KeyPackageStorageProtocol> {
+    Arc::new(ObservedStorage { inner, observer })
+}
+
+/// See [`observed_group_state_storage`] and
+/// [`observed_key_package_storage`].
+#[derive(Debug)]
+struct ObservedStorage<S: ?Sized> {
+    inner: Arc<S>,
+    observer: Arc<dyn StorageObserverProtocol>,
+}
+
+impl<S: ?Sized> ObservedStorage<S> {
+    fn report<T>(
+        &self,
+        operation: StorageOperationFFI,
+        group_id: Option<Vec<u8>>,
+        start: Instant,
+        result: &Result<T, MlSrsError>,
+        size: impl FnOnce(&T) -> usize,
+    ) {
+        self.observer.on_storage_event(StorageEventFFI {
+            operation,
+            group_id,
+            size: result.as_ref().map_or(0, size) as u64,
+            duration_micros: start.elapsed().as_micros() as u64,
+            success: result.is_ok(),
+        });
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl GroupStateStorageProtocol for ObservedStorage<dyn GroupStateStorageProtocol> {
+    async fn state(&self, group_id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError> {
+        let start = Instant::now();
+        let result = self.inner.state(group_id.clone()).await;
+        self.report(
+            StorageOperationFFI::ReadState,
+            Some(group_id),
+            start,
+            &result,
+            |data| data.as_ref().map_or(0, Vec::len),
+        );
+        result
+    }
+
+    async fn epoch(&self, group_id: Vec<u8>, epoch_id: u64) -> Result<Option<Vec<u8>>, MlSrsError> {
+        let start = Instant::now();
+        let result = self.inner.epoch(group_id.clone(), epoch_id).await;
+        self.report(
+            StorageOperationFFI::ReadEpoch,
+            Some(group_id),
+            start,
+            &result,
+            |data| data.as_ref().map_or(0, Vec::len),
+        );
+        result
+    }
+
+    async fn write(
+        &self,
+        group_id: Vec<u8>,
+        group_state: Vec<u8>,
+        epoch_inserts: Vec<EpochRecordFFI>,
+        epoch_updates: Vec<EpochRecordFFI>,
+        prune_before: Option<u64>,
+    ) -> Result<(), MlSrsError> {
+        let size = group_state.len()
+            + epoch_inserts
+                .iter()
+                .chain(&epoch_updates)
+                .map(|epoch| epoch.data.len())
+                .sum::<usize>();
+
+        let start = Instant::now();
+        let result = self
+            .inner
+            .write(
+                group_id.clone(),
+                group_state,
+                epoch_inserts,
+                epoch_updates,
+                prune_before,
+            )
+            .await;
+        self.report(
+            StorageOperationFFI::WriteGroup,
+            Some(group_id),
+            start,
+            &result,
+            |_| size,
+        );
+        result
+    }
+
+    async fn max_epoch_id(&self, group_id: Vec<u8>) -> Result<Option<u64>, MlSrsError> {
+        self.inner.max_epoch_id(group_id).await
+    }
+
+    async fn delete_group(&self, group_id: Vec<u8>) -> Result<(), MlSrsError> {
+        self.inner.delete_group(group_id).await
+    }
+
+    async fn group_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        self.inner.group_ids().await
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl KeyPackageStorageProtocol for ObservedStorage<dyn KeyPackageStorageProtocol> {
+    async fn delete(&self, id: Vec<u8>) -> Result<(), MlSrsError> {
+        self.inner.delete(id).await
+    }
+
+    async fn insert(&self, id: Vec<u8>, pkg: KeyPackageDataFFI) -> Result<(), MlSrsError> {
+        let size =
+            pkg.key_package_bytes.len() + pkg.init_key_data.len() + pkg.leaf_node_key_data.len();
+
+        let start = Instant::now();
+        let result = self.inner.insert(id, pkg).await;
+        self.report(
+            StorageOperationFFI::WriteKeyPackage,
+            None,
+            start,
+            &result,
+            |_| size,
+        );
+        result
+    }
+
+    async fn get(&self, id: Vec<u8>) -> Result<Option<KeyPackageDataFFI>, MlSrsError> {
+        let start = Instant::now();
+        let result = self.inner.get(id).await;
+        self.report(
+            StorageOperationFFI::ReadKeyPackage,
+            None,
+            start,
+            &result,
+            |pkg| {
+                pkg.as_ref().map_or(0, |pkg| {
+                    pkg.key_package_bytes.len()
+                        + pkg.init_key_data.len()
+                        + pkg.leaf_node_key_data.len()
+                })
+            },
+        );
+        result
+    }
+
+    async fn key_package_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        self.inner.key_package_ids().await
+    }
+}
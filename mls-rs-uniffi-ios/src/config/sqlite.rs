@@ -0,0 +1,224 @@
+use rusqlite::{params, Connection, OptionalExtension};
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zeroize::Zeroize;
+
+use crate::config::group_state::{
+    EpochRecordFFI, GroupStateStorageProtocol, KeyPackageDataFFI, KeyPackageStorageProtocol,
+};
+use crate::mls_rs_error::MlSrsError;
+
+/// Open a SQLite group state storage at `path`.
+///
+/// The database is created if it doesn't exist. The same file can be
+/// used with [`sqlite_key_package_storage`].
+#[uniffi::export]
+pub fn sqlite_group_state_storage(
+    path: String,
+) -> Result<Arc<dyn GroupStateStorageProtocol>, MlSrsError> {
+    Ok(Arc::new(SqliteGroupStateStorage::open(&path)?))
+}
+
+/// Open a SQLite key package storage at `path`.
+///
+/// The database is created if it doesn't exist. The same file can be
+/// used with [`sqlite_group_state_storage`].
+#[uniffi::export]
+pub fn sqlite_key_package_storage(
+    path: String,
+) -> Result<Arc<dyn KeyPackageStorageProtocol>, MlSrsError> {
+    Ok(Arc::new(SqliteGroupStateStorage::open(&path)?))
+}
+
+/// How long a connection waits for a lock held by another
+/// connection, e.g., the group state and key package storages opened
+/// on the same file, before failing with `SQLITE_BUSY`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Group state and key package storage backed by a SQLite file.
+#[derive(Debug)]
+pub struct SqliteGroupStateStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteGroupStateStorage {
+    /// Open or create the database at `path`.
+    pub fn open(path: &str) -> Result<Self, MlSrsError> {
+        let connection = Connection::open(path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS mls_group (
+                group_id BLOB PRIMARY KEY,
+                state BLOB NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS epoch (
+                group_id BLOB NOT NULL,
+                epoch_id INTEGER NOT NULL,
+                data BLOB NOT NULL,
+                PRIMARY KEY (group_id, epoch_id)
+            );
+            CREATE TABLE IF NOT EXISTS key_package (
+                id BLOB PRIMARY KEY,
+                key_package_bytes BLOB NOT NULL,
+                init_key_data BLOB NOT NULL,
+                leaf_node_key_data BLOB NOT NULL,
+                expiration INTEGER NOT NULL
+            );",
+        )?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap()
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl GroupStateStorageProtocol for SqliteGroupStateStorage {
+    async fn state(&self, group_id: Vec<u8>) -> Result<Option<Vec<u8>>, MlSrsError> {
+        Ok(self
+            .connection()
+            .query_row(
+                "SELECT state FROM mls_group WHERE group_id = ?",
+                params![group_id],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    async fn epoch(&self, group_id: Vec<u8>, epoch_id: u64) -> Result<Option<Vec<u8>>, MlSrsError> {
+        Ok(self
+            .connection()
+            .query_row(
+                "SELECT data FROM epoch WHERE group_id = ? AND epoch_id = ?",
+                params![group_id, epoch_id],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    async fn write(
+        &self,
+        group_id: Vec<u8>,
+        group_state: Vec<u8>,
+        epoch_inserts: Vec<EpochRecordFFI>,
+        epoch_updates: Vec<EpochRecordFFI>,
+        prune_before: Option<u64>,
+    ) -> Result<(), MlSrsError> {
+        let mut connection = self.connection();
+        let transaction = connection.savepoint()?;
+
+        transaction.execute(
+            "INSERT OR REPLACE INTO mls_group (group_id, state) VALUES (?, ?)",
+            params![group_id, group_state],
+        )?;
+        for epoch in epoch_inserts {
+            transaction.execute(
+                "INSERT INTO epoch (group_id, epoch_id, data) VALUES (?, ?, ?)",
+                params![group_id, epoch.id, epoch.data],
+            )?;
+        }
+        for epoch in epoch_updates {
+            transaction.execute(
+                "UPDATE epoch SET data = ? WHERE group_id = ? AND epoch_id = ?",
+                params![epoch.data, group_id, epoch.id],
+            )?;
+        }
+        if let Some(before_epoch_id) = prune_before {
+            transaction.execute(
+                "DELETE FROM epoch WHERE group_id = ? AND epoch_id < ?",
+                params![group_id, before_epoch_id],
+            )?;
+        }
+        Ok(transaction.commit()?)
+    }
+
+    async fn max_epoch_id(&self, group_id: Vec<u8>) -> Result<Option<u64>, MlSrsError> {
+        Ok(self.connection().query_row(
+            "SELECT MAX(epoch_id) FROM epoch WHERE group_id = ?",
+            params![group_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    async fn delete_group(&self, group_id: Vec<u8>) -> Result<(), MlSrsError> {
+        let mut connection = self.connection();
+        let transaction = connection.savepoint()?;
+        transaction.execute("DELETE FROM epoch WHERE group_id = ?", params![group_id])?;
+        transaction.execute(
+            "DELETE FROM mls_group WHERE group_id = ?",
+            params![group_id],
+        )?;
+        Ok(transaction.commit()?)
+    }
+
+    async fn group_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare("SELECT group_id FROM mls_group")?;
+        let group_ids = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(group_ids)
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl KeyPackageStorageProtocol for SqliteGroupStateStorage {
+    async fn delete(&self, id: Vec<u8>) -> Result<(), MlSrsError> {
+        self.connection()
+            .execute("DELETE FROM key_package WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    async fn insert(&self, id: Vec<u8>, mut pkg: KeyPackageDataFFI) -> Result<(), MlSrsError> {
+        let result = self.connection().execute(
+            "INSERT OR REPLACE INTO key_package \
+             (id, key_package_bytes, init_key_data, leaf_node_key_data, expiration) \
+             VALUES (?, ?, ?, ?, ?)",
+            params![
+                id,
+                pkg.key_package_bytes,
+                pkg.init_key_data,
+                pkg.leaf_node_key_data,
+                pkg.expiration
+            ],
+        );
+        pkg.zeroize();
+        result?;
+        Ok(())
+    }
+
+    async fn get(&self, id: Vec<u8>) -> Result<Option<KeyPackageDataFFI>, MlSrsError> {
+        Ok(self
+            .connection()
+            .query_row(
+                "SELECT key_package_bytes, init_key_data, leaf_node_key_data, expiration \
+                 FROM key_package WHERE id = ?",
+                params![id],
+                |row| {
+                    Ok(KeyPackageDataFFI {
+                        key_package_bytes: row.get(0)?,
+                        init_key_data: row.get(1)?,
+                        leaf_node_key_data: row.get(2)?,
+                        expiration: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    async fn key_package_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare("SELECT id FROM key_package")?;
+        let ids = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(ids)
+    }
+}
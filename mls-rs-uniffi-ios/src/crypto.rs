@@ -0,0 +1,313 @@
+use crate::config::crypto_provider::{
+    CipherSuiteProviderWrapper, CryptoProviderAdapter, CryptoProviderProtocol,
+    CryptoProviderWrapper,
+};
+use crate::config::group_context::CipherSuiteFFI;
+use crate::MlSrsError;
+
+use mls_rs::crypto::{HpkeCiphertext, HpkePublicKey, HpkeSecretKey};
+use mls_rs::error::MlsError;
+use mls_rs::{CipherSuiteProvider, CryptoProvider};
+use std::fmt::Debug;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+/// The crypto provider used when none is configured.
+///
+/// Selected by the `cryptokit`, `rustcrypto` and `openssl` features.
+#[cfg(feature = "cryptokit")]
+pub(crate) type DefaultCryptoProvider = mls_rs_crypto_cryptokit::CryptoKitProvider;
+#[cfg(all(feature = "rustcrypto", not(feature = "cryptokit")))]
+pub(crate) type DefaultCryptoProvider = mls_rs_crypto_rustcrypto::RustCryptoProvider;
+#[cfg(all(
+    feature = "openssl",
+    not(any(feature = "cryptokit", feature = "rustcrypto"))
+))]
+pub(crate) type DefaultCryptoProvider = mls_rs_crypto_openssl::OpensslCryptoProvider;
+
+#[cfg(not(any(feature = "cryptokit", feature = "rustcrypto", feature = "openssl")))]
+compile_error!("one of the features cryptokit, rustcrypto or openssl must be enabled");
+
+/// The [`DefaultCryptoProvider`] as a [`CryptoProviderProtocol`].
+pub(crate) fn default_crypto_provider() -> Arc<dyn CryptoProviderProtocol> {
+    Arc::new(CryptoProviderAdapter::new(DefaultCryptoProvider::default()))
+}
+
+/// The cipher suite provider of `crypto_provider`, or of the
+/// [`DefaultCryptoProvider`] if it is `None`.
+///
+/// The free functions of this module take the provider as an
+/// argument: pass
+/// [`ClientConfigFFI::crypto_provider`](crate::config::ClientConfigFFI::crypto_provider)
+/// to use the same provider as a client.
+pub(crate) fn provider_cipher_suite_provider(
+    crypto_provider: Option<Arc<dyn CryptoProviderProtocol>>,
+    cipher_suite: CipherSuiteFFI,
+) -> Result<CipherSuiteProviderWrapper, MlSrsError> {
+    CryptoProviderWrapper::from(crypto_provider.unwrap_or_else(default_crypto_provider))
+        .cipher_suite_provider(cipher_suite.into())
+        .ok_or(MlsError::UnsupportedCipherSuite(cipher_suite.into()).into())
+}
+
+/// A HPKE keypair of a cipher suite.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct HpkeKeypairFFI {
+    pub cipher_suite: CipherSuiteFFI,
+    pub public_key: Vec<u8>,
+    pub secret_key: Vec<u8>,
+}
+
+/// A [`mls_rs::crypto::HpkeCiphertext`] wrapper.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct HpkeCiphertextFFI {
+    pub kem_output: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+impl From<HpkeCiphertext> for HpkeCiphertextFFI {
+    fn from(ciphertext: HpkeCiphertext) -> Self {
+        Self {
+            kem_output: ciphertext.kem_output,
+            ciphertext: ciphertext.ciphertext,
+        }
+    }
+}
+
+impl From<HpkeCiphertextFFI> for HpkeCiphertext {
+    fn from(ciphertext: HpkeCiphertextFFI) -> Self {
+        Self {
+            kem_output: ciphertext.kem_output,
+            ciphertext: ciphertext.ciphertext,
+        }
+    }
+}
+
+/// Generate a HPKE keypair with `crypto_provider`, or the built-in
+/// provider if it is `None`.
+///
+/// See [`mls_rs::CipherSuiteProvider::kem_generate`] for details.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export(default(crypto_provider = None))]
+pub async fn generate_hpke_keypair(
+    cipher_suite: CipherSuiteFFI,
+    crypto_provider: Option<Arc<dyn CryptoProviderProtocol>>,
+) -> Result<HpkeKeypairFFI, MlSrsError> {
+    let cipher_suite_provider = provider_cipher_suite_provider(crypto_provider, cipher_suite)?;
+
+    let (secret_key, public_key) = cipher_suite_provider.kem_generate().await?;
+
+    Ok(HpkeKeypairFFI {
+        cipher_suite,
+        public_key: public_key.to_vec(),
+        secret_key: secret_key.to_vec(),
+    })
+}
+
+/// Encrypt `plaintext` to the HPKE public key `public_key` in a
+/// single shot with `crypto_provider`, or the built-in provider if it
+/// is `None`.
+///
+/// See [`mls_rs::CipherSuiteProvider::hpke_seal`] for details.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export(default(crypto_provider = None))]
+pub async fn hpke_seal(
+    cipher_suite: CipherSuiteFFI,
+    public_key: Vec<u8>,
+    info: Vec<u8>,
+    aad: Option<Vec<u8>>,
+    plaintext: Vec<u8>,
+    crypto_provider: Option<Arc<dyn CryptoProviderProtocol>>,
+) -> Result<HpkeCiphertextFFI, MlSrsError> {
+    let cipher_suite_provider = provider_cipher_suite_provider(crypto_provider, cipher_suite)?;
+
+    let ciphertext = cipher_suite_provider
+        .hpke_seal(
+            &HpkePublicKey::from(public_key),
+            &info,
+            aad.as_deref(),
+            &plaintext,
+        )
+        .await?;
+
+    Ok(ciphertext.into())
+}
+
+/// Decrypt a ciphertext created with [`hpke_seal`] with
+/// `crypto_provider`, or the built-in provider if it is `None`.
+///
+/// See [`mls_rs::CipherSuiteProvider::hpke_open`] for details.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export(default(crypto_provider = None))]
+pub async fn hpke_open(
+    keypair: HpkeKeypairFFI,
+    info: Vec<u8>,
+    aad: Option<Vec<u8>>,
+    ciphertext: HpkeCiphertextFFI,
+    crypto_provider: Option<Arc<dyn CryptoProviderProtocol>>,
+) -> Result<Vec<u8>, MlSrsError> {
+    let cipher_suite_provider =
+        provider_cipher_suite_provider(crypto_provider, keypair.cipher_suite)?;
+
+    let plaintext = cipher_suite_provider
+        .hpke_open(
+            &ciphertext.into(),
+            &HpkeSecretKey::from(keypair.secret_key),
+            &HpkePublicKey::from(keypair.public_key),
+            &info,
+            aad.as_deref(),
+        )
+        .await?;
+
+    Ok(plaintext.to_vec())
+}
+
+/// Derive `length` bytes from `ikm` with the cipher suite's KDF.
+///
+/// This runs HKDF-Extract with `salt` followed by HKDF-Expand with
+/// `info`, matching the KDF used by groups of this cipher suite. It
+/// can be used to further separate secrets revealed from
+/// [`GroupFFI::export_secret`](crate::group::GroupFFI::export_secret),
+/// see also [`SecretFFI::expand`]. The KDF of `crypto_provider` is
+/// used, or of the built-in provider if it is `None`.
+///
+/// See [`mls_rs::CipherSuiteProvider::kdf_extract`] and
+/// [`mls_rs::CipherSuiteProvider::kdf_expand`] for details.
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export(default(crypto_provider = None))]
+pub async fn hkdf_extract_expand(
+    cipher_suite: CipherSuiteFFI,
+    ikm: Vec<u8>,
+    salt: Vec<u8>,
+    info: Vec<u8>,
+    length: u32,
+    crypto_provider: Option<Arc<dyn CryptoProviderProtocol>>,
+) -> Result<Vec<u8>, MlSrsError> {
+    let cipher_suite_provider = provider_cipher_suite_provider(crypto_provider, cipher_suite)?;
+
+    let prk = cipher_suite_provider.kdf_extract(&salt, &ikm).await?;
+    let okm = cipher_suite_provider
+        .kdf_expand(&prk, &info, length as usize)
+        .await?;
+
+    Ok(okm.to_vec())
+}
+
+/// Generate `len` random bytes with the RNG of `crypto_provider`, or
+/// of the built-in provider if it is `None`.
+///
+/// See [`mls_rs::CipherSuiteProvider::random_bytes_vec`] for details.
+#[uniffi::export(default(crypto_provider = None))]
+pub fn random_bytes(
+    len: u32,
+    crypto_provider: Option<Arc<dyn CryptoProviderProtocol>>,
+) -> Result<Vec<u8>, MlSrsError> {
+    let crypto_provider = crypto_provider.unwrap_or_else(default_crypto_provider);
+    // The RNG does not depend on the cipher suite, so any supported
+    // suite will do.
+    let cipher_suite = crypto_provider
+        .supported_cipher_suites()
+        .into_iter()
+        .next()
+        .ok_or(MlSrsError::NotImplemented)?;
+
+    provider_cipher_suite_provider(Some(crypto_provider), cipher_suite)?
+        .random_bytes_vec(len as usize)
+}
+
+/// A secret which is zeroized when dropped.
+///
+/// Returned by
+/// [`GroupFFI::export_secret`](crate::group::GroupFFI::export_secret).
+/// Derive further secrets with [`Self::expand`] and compare them
+/// with [`Self::constant_time_eq`] so the bytes only need to be
+/// revealed where they are actually used.
+#[derive(uniffi::Object)]
+pub struct SecretFFI {
+    cipher_suite: CipherSuiteFFI,
+    /// Provider of the group the secret was exported from.
+    cipher_suite_provider: CipherSuiteProviderWrapper,
+    bytes: Zeroizing<Vec<u8>>,
+}
+
+impl SecretFFI {
+    pub(crate) fn new(
+        cipher_suite: CipherSuiteFFI,
+        cipher_suite_provider: CipherSuiteProviderWrapper,
+        bytes: Zeroizing<Vec<u8>>,
+    ) -> Self {
+        Self {
+            cipher_suite,
+            cipher_suite_provider,
+            bytes,
+        }
+    }
+}
+
+impl Debug for SecretFFI {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretFFI")
+            .field("cipher_suite", &self.cipher_suite)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+impl SecretFFI {
+    /// Copy the secret bytes.
+    ///
+    /// The copy is not zeroized, prefer the other methods where
+    /// possible.
+    pub fn reveal(&self) -> Vec<u8> {
+        self.bytes.to_vec()
+    }
+
+    /// The cipher suite of the group the secret was exported from.
+    pub fn cipher_suite(&self) -> CipherSuiteFFI {
+        self.cipher_suite
+    }
+
+    /// Compare two secrets in time independent of their contents.
+    pub fn constant_time_eq(&self, other: Arc<SecretFFI>) -> bool {
+        if self.bytes.len() != other.bytes.len() {
+            return false;
+        }
+        let difference = self
+            .bytes
+            .iter()
+            .zip(other.bytes.iter())
+            .fold(0u8, |difference, (a, b)| difference | (a ^ b));
+        std::hint::black_box(difference) == 0
+    }
+
+    /// Derive a secret of `length` bytes with HKDF-Expand of the
+    /// cipher suite's KDF, using this secret as the PRK. The KDF of
+    /// the group's crypto provider is used.
+    ///
+    /// See [`mls_rs::CipherSuiteProvider::kdf_expand`] for details.
+    pub async fn expand(&self, info: Vec<u8>, length: u32) -> Result<Arc<SecretFFI>, MlSrsError> {
+        let okm = self
+            .cipher_suite_provider
+            .kdf_expand(&self.bytes, &info, length as usize)
+            .await?;
+
+        Ok(Arc::new(SecretFFI::new(
+            self.cipher_suite,
+            self.cipher_suite_provider.clone(),
+            okm,
+        )))
+    }
+}
+
+pub(crate) fn cipher_suite_provider(
+    cipher_suite: CipherSuiteFFI,
+) -> Result<impl CipherSuiteProvider, MlSrsError> {
+    DefaultCryptoProvider::default()
+        .cipher_suite_provider(cipher_suite.into())
+        .ok_or(MlsError::UnsupportedCipherSuite(cipher_suite.into()).into())
+}
@@ -0,0 +1,396 @@
+use crate::config::group_context::ExtensionListFFI;
+use crate::config::group_context::GroupContextFFI;
+use crate::config::member_validation_context::MemberValidationContextFFI;
+use crate::config::{IdentityProviderProtocol, IdentityProviderStorage, SigningIdentityFFI};
+use crate::crypto::DefaultCryptoProvider;
+use crate::group::MLSMemberFFI;
+use crate::message::{CommitEffectFFI, KeyPackageFFI, MessageFFI, ProposalFFI, ProposalSenderFFI};
+use crate::{arc_unwrap_or_clone, MlSrsError};
+
+use std::sync::Arc;
+
+#[cfg(not(mls_build_async))]
+use std::sync::Mutex;
+#[cfg(mls_build_async)]
+use tokio::sync::Mutex;
+
+use mls_rs::error::{IntoAnyError, MlsError};
+use mls_rs::external_client::builder::{
+    ExternalBaseConfig, WithCryptoProvider, WithIdentityProvider,
+};
+use mls_rs::external_client::{
+    ExternalClient, ExternalGroup, ExternalReceivedMessage, ExternalSnapshot,
+};
+use mls_rs::group::proposal::Proposal;
+use mls_rs::group::ProposalSender;
+use mls_rs::{CipherSuiteProvider, CryptoProvider};
+use mls_rs_core::identity::{BasicCredential, SigningIdentity};
+
+pub type UniFFIExternalConfig = WithIdentityProvider<
+    IdentityProviderStorage,
+    WithCryptoProvider<DefaultCryptoProvider, ExternalBaseConfig>,
+>;
+
+/// An MLS client which validates messages without being a group
+/// member, e.g., on a delivery service.
+///
+/// See [`mls_rs::external_client::ExternalClient`] for details.
+#[derive(Clone, Debug, uniffi::Object)]
+pub struct ExternalClientFFI {
+    inner: ExternalClient<UniFFIExternalConfig>,
+    identity_provider: Arc<dyn IdentityProviderProtocol>,
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+impl ExternalClientFFI {
+    /// Create a new external client.
+    ///
+    /// Identities found in key packages and group infos are validated
+    /// with `identity_provider`.
+    #[uniffi::constructor]
+    pub fn new(identity_provider: Arc<dyn IdentityProviderProtocol>) -> Self {
+        let inner = ExternalClient::builder()
+            .crypto_provider(DefaultCryptoProvider::default())
+            .identity_provider(identity_provider.clone().into())
+            .build();
+
+        Self {
+            inner,
+            identity_provider,
+        }
+    }
+
+    /// Start observing the group described by a group info message.
+    ///
+    /// The group info must contain the ratchet tree extension.
+    ///
+    /// See [`mls_rs::external_client::ExternalClient::observe_group`]
+    /// for details.
+    pub async fn observe_group(
+        &self,
+        group_info: Arc<MessageFFI>,
+    ) -> Result<ExternalGroupFFI, MlSrsError> {
+        let group = self
+            .inner
+            .observe_group(group_info.inner.clone(), None, None)
+            .await?;
+        Ok(group.into())
+    }
+
+    /// Load a group serialized with [`ExternalGroupFFI::snapshot`].
+    pub async fn load_group(&self, snapshot: Vec<u8>) -> Result<ExternalGroupFFI, MlSrsError> {
+        let snapshot = ExternalSnapshot::from_bytes(&snapshot)?;
+        let group = self.inner.load_group(snapshot).await?;
+        Ok(group.into())
+    }
+
+    /// Validate a group info message and return its group context.
+    ///
+    /// This checks the group info signature and the ratchet tree
+    /// found in its ratchet tree extension.
+    ///
+    /// See [`mls_rs::external_client::ExternalClient::observe_group`]
+    /// for details.
+    pub async fn validate_group_info(
+        &self,
+        group_info: Arc<MessageFFI>,
+    ) -> Result<GroupContextFFI, MlSrsError> {
+        let group = self
+            .inner
+            .observe_group(group_info.inner.clone(), None, None)
+            .await?;
+        group.group_context().clone().try_into()
+    }
+
+    /// Validate a key package message.
+    ///
+    /// mls-rs validates key packages when they are added to a group,
+    /// so the key package is added to a throwaway group with a random
+    /// creator. This runs all checks of mls-rs: version, cipher suite,
+    /// capabilities, signatures, keys, the leaf node lifetime at the
+    /// current time and the identity with the identity provider. If a
+    /// `timestamp` (seconds since the Unix epoch) is given, the
+    /// lifetime must also include it.
+    ///
+    /// Creating the group costs a few key generations and signatures,
+    /// so this is slower than parsing the key package.
+    pub async fn validate_key_package(
+        &self,
+        key_package: Arc<MessageFFI>,
+        timestamp: Option<u64>,
+    ) -> Result<Arc<KeyPackageFFI>, MlSrsError> {
+        let key_package_message = arc_unwrap_or_clone(key_package).inner;
+        let key_package = key_package_message
+            .clone()
+            .into_key_package()
+            .ok_or(MlSrsError::UnexpecteMessageFormat)?;
+        let cipher_suite = key_package.cipher_suite;
+        let cipher_suite_provider = DefaultCryptoProvider::default()
+            .cipher_suite_provider(cipher_suite)
+            .ok_or(MlsError::UnsupportedCipherSuite(cipher_suite))?;
+
+        let (secret_key, public_key) = cipher_suite_provider
+            .signature_key_generate()
+            .await
+            .map_err(|err| MlsError::CryptoProviderError(err.into_any_error()))?;
+        let creator_id = cipher_suite_provider
+            .random_bytes_vec(32)
+            .map_err(|err| MlsError::CryptoProviderError(err.into_any_error()))?;
+        let creator = SigningIdentity::new(
+            BasicCredential::new(creator_id).into_credential(),
+            public_key,
+        );
+        let identity_provider: Arc<dyn IdentityProviderProtocol> =
+            Arc::new(KeyPackageCheckIdentityProvider {
+                creator: creator.clone(),
+                inner: self.identity_provider.clone(),
+            });
+        let client = mls_rs::Client::builder()
+            .crypto_provider(DefaultCryptoProvider::default())
+            .identity_provider(IdentityProviderStorage::from(identity_provider))
+            .signing_identity(creator, secret_key, cipher_suite)
+            .build();
+        let mut group = client
+            .create_group(mls_rs::ExtensionList::new(), mls_rs::ExtensionList::new())
+            .await?;
+        group
+            .commit_builder()
+            .add_member(key_package_message)?
+            .build()
+            .await?;
+
+        let key_package: KeyPackageFFI = key_package.try_into()?;
+        if let (Some(timestamp), Some(lifetime)) = (timestamp, &key_package.lifetime) {
+            if timestamp < lifetime.not_before || timestamp > lifetime.not_after {
+                return Err(MlSrsError::KeyPackageExpired);
+            }
+        }
+        Ok(Arc::new(key_package))
+    }
+}
+
+/// The identity provider of the throwaway group in
+/// [`ExternalClientFFI::validate_key_package`].
+///
+/// The random creator of the group is accepted, all other identities
+/// are passed to the identity provider of the external client.
+#[derive(Debug)]
+struct KeyPackageCheckIdentityProvider {
+    creator: SigningIdentity,
+    inner: Arc<dyn IdentityProviderProtocol>,
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl IdentityProviderProtocol for KeyPackageCheckIdentityProvider {
+    async fn validate_member(
+        &self,
+        signing_identity: Arc<SigningIdentityFFI>,
+        timestamp: Option<u64>,
+        context: MemberValidationContextFFI,
+    ) -> Result<(), MlSrsError> {
+        if signing_identity.inner == self.creator {
+            return Ok(());
+        }
+        self.inner
+            .validate_member(signing_identity, timestamp, context)
+            .await
+    }
+
+    async fn validate_external_sender(
+        &self,
+        signing_identity: Arc<SigningIdentityFFI>,
+        timestamp: Option<u64>,
+        extensions: Option<Arc<ExtensionListFFI>>,
+    ) -> Result<(), MlSrsError> {
+        self.inner
+            .validate_external_sender(signing_identity, timestamp, extensions)
+            .await
+    }
+
+    async fn identity(
+        &self,
+        signing_identity: Arc<SigningIdentityFFI>,
+        extensions: Arc<ExtensionListFFI>,
+    ) -> Result<Vec<u8>, MlSrsError> {
+        if signing_identity.inner == self.creator {
+            // The random id of the basic credential.
+            return signing_identity
+                .basic_credential()
+                .ok_or(MlSrsError::MissingBasicCredential);
+        }
+        self.inner.identity(signing_identity, extensions).await
+    }
+
+    async fn valid_successor(
+        &self,
+        predecessor: Arc<SigningIdentityFFI>,
+        successor: Arc<SigningIdentityFFI>,
+        extensions: Arc<ExtensionListFFI>,
+    ) -> Result<bool, MlSrsError> {
+        self.inner
+            .valid_successor(predecessor, successor, extensions)
+            .await
+    }
+
+    fn supported_types(&self) -> Vec<u16> {
+        let mut supported_types = self.inner.supported_types();
+        // The creator has a basic credential.
+        if !supported_types.contains(&1) {
+            supported_types.push(1);
+        }
+        supported_types
+    }
+}
+
+/// A group observed by an [`ExternalClientFFI`].
+///
+/// The group tracks the public group state and roster by processing
+/// handshake messages, without holding any group secrets.
+///
+/// See [`mls_rs::external_client::ExternalGroup`] for details.
+#[derive(Clone, uniffi::Object)]
+pub struct ExternalGroupFFI {
+    inner: Arc<Mutex<ExternalGroup<UniFFIExternalConfig>>>,
+}
+
+impl ExternalGroupFFI {
+    #[cfg(not(mls_build_async))]
+    fn inner(&self) -> std::sync::MutexGuard<'_, ExternalGroup<UniFFIExternalConfig>> {
+        self.inner.lock().unwrap()
+    }
+
+    #[cfg(mls_build_async)]
+    async fn inner(&self) -> tokio::sync::MutexGuard<'_, ExternalGroup<UniFFIExternalConfig>> {
+        self.inner.lock().await
+    }
+}
+
+impl From<ExternalGroup<UniFFIExternalConfig>> for ExternalGroupFFI {
+    fn from(inner: ExternalGroup<UniFFIExternalConfig>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+impl ExternalGroupFFI {
+    /// Process a handshake message for this group.
+    ///
+    /// Commits advance the observed group to the next epoch, so they
+    /// must be processed in order. Application messages can't be
+    /// decrypted and are only described by their content type.
+    pub async fn process_incoming_message(
+        &self,
+        message: Arc<MessageFFI>,
+    ) -> Result<ExternalReceivedMessageFFI, MlSrsError> {
+        let message = arc_unwrap_or_clone(message);
+        let mut group = self.inner().await;
+        match group.process_incoming_message(message.inner).await? {
+            ExternalReceivedMessage::Commit(commit_message) => {
+                Ok(ExternalReceivedMessageFFI::Commit {
+                    committer: commit_message.committer,
+                    effect: CommitEffectFFI::new(commit_message.effect, &group.roster()),
+                    authenticated_data: commit_message.authenticated_data,
+                })
+            }
+            ExternalReceivedMessage::Proposal(proposal_message) => {
+                let sender = match proposal_message.sender {
+                    ProposalSender::Member(index) => {
+                        let member = group.roster().member_with_index(index)?;
+                        ProposalSenderFFI::Member(Arc::new(member.signing_identity.into()))
+                    }
+                    ProposalSender::External(index) => ProposalSenderFFI::External { index },
+                    ProposalSender::NewMember => {
+                        let Proposal::Add(add_proposal) = &proposal_message.proposal else {
+                            return Err(MlSrsError::UnexpectedProposalSender);
+                        };
+                        let key_package = add_proposal.key_package().clone().try_into()?;
+                        ProposalSenderFFI::NewMember {
+                            key_package: Arc::new(key_package),
+                        }
+                    }
+                };
+                let authenticated_data = proposal_message.authenticated_data.clone();
+                let proposal_ref = proposal_message.proposal_ref.to_vec();
+                let proposal = proposal_message.try_into()?;
+                Ok(ExternalReceivedMessageFFI::Proposal {
+                    sender,
+                    proposal,
+                    proposal_ref,
+                    authenticated_data,
+                })
+            }
+            ExternalReceivedMessage::Ciphertext(content_type) => {
+                Ok(ExternalReceivedMessageFFI::Ciphertext {
+                    content_type: content_type as u8,
+                })
+            }
+            ExternalReceivedMessage::GroupInfo(_) => Ok(ExternalReceivedMessageFFI::GroupInfo),
+            ExternalReceivedMessage::Welcome => Ok(ExternalReceivedMessageFFI::Welcome),
+            ExternalReceivedMessage::KeyPackage(key_package) => {
+                Ok(ExternalReceivedMessageFFI::KeyPackage {
+                    key_package: Arc::new(key_package.try_into()?),
+                })
+            }
+        }
+    }
+
+    /// Serialize the observed group state, see
+    /// [`ExternalClientFFI::load_group`].
+    pub async fn snapshot(&self) -> Result<Vec<u8>, MlSrsError> {
+        Ok(self.inner().await.snapshot().to_bytes()?)
+    }
+
+    pub async fn group_context(&self) -> Result<GroupContextFFI, MlSrsError> {
+        self.inner().await.group_context().clone().try_into()
+    }
+
+    pub async fn group_id(&self) -> Vec<u8> {
+        self.inner().await.group_context().group_id.clone()
+    }
+
+    pub async fn current_epoch(&self) -> u64 {
+        self.inner().await.group_context().epoch
+    }
+
+    pub async fn members(&self) -> Vec<Arc<MLSMemberFFI>> {
+        self.inner()
+            .await
+            .roster()
+            .members_iter()
+            .map(|member| Arc::new(member.into()))
+            .collect()
+    }
+}
+
+/// A [`mls_rs::external_client::ExternalReceivedMessage`] wrapper.
+#[derive(Clone, Debug, uniffi::Enum)]
+pub enum ExternalReceivedMessageFFI {
+    /// A commit was processed, advancing the observed group state.
+    Commit {
+        committer: u32,
+        effect: CommitEffectFFI,
+        authenticated_data: Vec<u8>,
+    },
+    /// A proposal was received.
+    Proposal {
+        sender: ProposalSenderFFI,
+        proposal: ProposalFFI,
+        proposal_ref: Vec<u8>,
+        authenticated_data: Vec<u8>,
+    },
+    /// An encrypted message which can't be read by an observer.
+    Ciphertext { content_type: u8 },
+    /// Validated GroupInfo object.
+    GroupInfo,
+    /// Validated welcome message.
+    Welcome,
+    /// Validated key package.
+    KeyPackage { key_package: Arc<KeyPackageFFI> },
+}
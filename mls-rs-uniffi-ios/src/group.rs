@@ -1,15 +1,35 @@
 use crate::arc_unwrap_or_clone;
-use crate::config::{SignatureSecretKeyFFI, SigningIdentityFFI};
-use crate::message::{ProposalFFI, ReceivedMessageFFI};
+use crate::config::{IdentityProviderProtocol, SignatureSecretKeyFFI, SigningIdentityFFI};
+use crate::crypto::{provider_cipher_suite_provider, SecretFFI};
+use crate::message::{
+    CapabilitiesFFI, CommitEffectFFI, ProposalInfoFFI, ProposalSenderFFI, ReceivedMessageFFI,
+};
 use crate::MlSrsError;
-use mls_rs::mls_rs_codec::MlsDecode;
-use mls_rs::psk::ExternalPskId;
-use std::sync::{Arc, Mutex};
+use mls_rs::mls_rs_codec::MlsEncode;
+use mls_rs::storage_provider::in_memory::InMemoryGroupStateStorage;
+use mls_rs_core::extension::ExtensionType;
+use mls_rs_core::group::ProposalType;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::Instant;
+use zeroize::Zeroizing;
 
-use crate::config::UniFFIConfig;
+#[cfg(not(mls_build_async))]
+use std::sync::RwLock;
+#[cfg(mls_build_async)]
+use tokio::sync::RwLock;
+
+use crate::config::crypto_provider::CryptoProviderProtocol;
+use crate::config::group_state::{
+    ExternalPskIdFFI, GroupSnapshot, GroupStateStorageAdapter, GroupStateStorageProtocol,
+};
+use crate::config::metrics::{MetricFFI, Metrics};
+use crate::config::mls_rules::{with_padding, PaddingModeFFI, UniFFIMlsRules};
+use crate::config::{with_group_state_storage, UniFFIConfig};
 use crate::message::MessageFFI;
 use crate::ExtensionListFFI;
-use mls_rs::group::ReceivedMessage;
+use mls_rs::group::proposal::Proposal;
+use mls_rs::group::{ReceivedMessage, Roster};
 
 /// An MLS end-to-end encrypted group.
 ///
@@ -19,13 +39,280 @@ use mls_rs::group::ReceivedMessage;
 /// See [`mls_rs::Group`] for details.
 #[derive(Clone, uniffi::Object)]
 pub struct GroupFFI {
-    pub(crate) inner: Arc<Mutex<mls_rs::Group<UniFFIConfig>>>,
+    /// The group state, `None` once the client was wiped.
+    pub(crate) inner: Arc<RwLock<Option<mls_rs::Group<UniFFIConfig>>>>,
+    pub(crate) mls_rules: UniFFIMlsRules,
+    pub(crate) metrics: Metrics,
+    pub(crate) identity_provider: Arc<dyn IdentityProviderProtocol>,
+    pub(crate) crypto_provider: Arc<dyn CryptoProviderProtocol>,
+    /// Welcome messages of our last commit, see
+    /// [`GroupFFI::pending_welcome_messages`].
+    pub(crate) pending_welcomes: Arc<std::sync::Mutex<Vec<mls_rs::MlsMessage>>>,
+    /// Proposals added to the proposal cache with the epoch they were
+    /// added in, see [`GroupDebugInfoFFI::pending_proposals`].
+    pub(crate) cached_proposals: Arc<std::sync::Mutex<Vec<(u64, PendingProposalFFI)>>>,
+    /// Member indices in roster order and the tree hash they were
+    /// collected for, see [`GroupFFI::roster_indices`].
+    pub(crate) roster_cache: Arc<std::sync::Mutex<Option<(Vec<u8>, Arc<Vec<u32>>)>>>,
+}
+
+/// A lock on a group which was not wiped, see
+/// [`ClientFFI::wipe`](crate::client::ClientFFI::wipe).
+pub(crate) struct GroupGuard<G>(G);
+
+impl<G: Deref<Target = Option<mls_rs::Group<UniFFIConfig>>>> GroupGuard<G> {
+    fn new(guard: G) -> Result<Self, MlSrsError> {
+        match guard.is_some() {
+            true => Ok(Self(guard)),
+            false => Err(MlSrsError::Wiped),
+        }
+    }
+}
+
+impl<G: Deref<Target = Option<mls_rs::Group<UniFFIConfig>>>> Deref for GroupGuard<G> {
+    type Target = mls_rs::Group<UniFFIConfig>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref().expect("checked in GroupGuard::new")
+    }
+}
+
+impl<G: DerefMut<Target = Option<mls_rs::Group<UniFFIConfig>>>> DerefMut for GroupGuard<G> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.as_mut().expect("checked in GroupGuard::new")
+    }
 }
 
-#[maybe_async::must_be_sync]
 impl GroupFFI {
-    fn inner(&self) -> std::sync::MutexGuard<'_, mls_rs::Group<UniFFIConfig>> {
-        self.inner.lock().unwrap()
+    /// Member indices of `group` in roster order.
+    ///
+    /// The roster only changes together with the ratchet tree, so the
+    /// indices are collected once per tree hash. This makes counting
+    /// and positional access O(1) after the first call in an epoch.
+    fn roster_indices(&self, group: &mls_rs::Group<UniFFIConfig>) -> Arc<Vec<u32>> {
+        let tree_hash = &group.context().tree_hash;
+        let mut cache = self.roster_cache.lock().unwrap();
+        match &*cache {
+            Some((cached_tree_hash, indices)) if cached_tree_hash == tree_hash => indices.clone(),
+            _ => {
+                let indices: Arc<Vec<u32>> = Arc::new(
+                    group
+                        .roster()
+                        .members_iter()
+                        .map(|member| member.index)
+                        .collect(),
+                );
+                *cache = Some((tree_hash.clone(), indices.clone()));
+                indices
+            }
+        }
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl GroupFFI {
+    /// Lock the group for an operation changing it.
+    #[cfg(not(mls_build_async))]
+    fn inner(
+        &self,
+    ) -> Result<
+        GroupGuard<std::sync::RwLockWriteGuard<'_, Option<mls_rs::Group<UniFFIConfig>>>>,
+        MlSrsError,
+    > {
+        GroupGuard::new(self.inner.write().unwrap())
+    }
+
+    #[cfg(mls_build_async)]
+    async fn inner(
+        &self,
+    ) -> Result<
+        GroupGuard<tokio::sync::RwLockWriteGuard<'_, Option<mls_rs::Group<UniFFIConfig>>>>,
+        MlSrsError,
+    > {
+        GroupGuard::new(self.inner.write().await)
+    }
+
+    /// Lock the group for reading, concurrently with other readers.
+    #[cfg(not(mls_build_async))]
+    fn read(
+        &self,
+    ) -> Result<
+        GroupGuard<std::sync::RwLockReadGuard<'_, Option<mls_rs::Group<UniFFIConfig>>>>,
+        MlSrsError,
+    > {
+        GroupGuard::new(self.inner.read().unwrap())
+    }
+
+    #[cfg(mls_build_async)]
+    async fn read(
+        &self,
+    ) -> Result<
+        GroupGuard<tokio::sync::RwLockReadGuard<'_, Option<mls_rs::Group<UniFFIConfig>>>>,
+        MlSrsError,
+    > {
+        GroupGuard::new(self.inner.read().await)
+    }
+
+    /// Record a proposal added to the proposal cache in `epoch`.
+    fn cache_proposal(
+        &self,
+        epoch: u64,
+        proposal_ref: Option<Vec<u8>>,
+        proposal_type: ProposalType,
+    ) {
+        let mut cached_proposals = self.cached_proposals.lock().unwrap();
+        cached_proposals.retain(|(cached_epoch, _)| *cached_epoch == epoch);
+        cached_proposals.push((
+            epoch,
+            PendingProposalFFI {
+                proposal_ref,
+                proposal_type: proposal_type.raw_value(),
+            },
+        ));
+    }
+
+    /// Process a message, shared by the `process_incoming_message*`
+    /// methods.
+    pub(crate) async fn process_message(
+        &self,
+        message: mls_rs::MlsMessage,
+    ) -> Result<ReceivedMessageFFI, MlSrsError> {
+        let message_epoch = message.epoch();
+        let cipher_suite = message.cipher_suite();
+        let key_package_references: Vec<Vec<u8>> = message
+            .welcome_key_package_references()
+            .into_iter()
+            .map(|reference| reference.to_vec())
+            .collect();
+        let mut group = self.inner().await?;
+        self.mls_rules
+            .check_message_epoch(message_epoch, group.current_epoch())?;
+        let start = Instant::now();
+        let received_message = group.process_incoming_message(message).await;
+        self.metrics
+            .report_timed(start, &received_message, |duration_micros, success| {
+                MetricFFI::MessageProcessed {
+                    duration_micros,
+                    success,
+                }
+            });
+        let received_message = received_message.map_err(|err| match MlSrsError::from(err) {
+            MlSrsError::WrongEpoch { .. } => MlSrsError::WrongEpoch {
+                message_epoch,
+                group_epoch: Some(group.current_epoch()),
+            },
+            err => err,
+        })?;
+        match received_message {
+            ReceivedMessage::ApplicationMessage(application_message) => {
+                let sender_index = application_message.sender_index;
+                let sender = Arc::new(index_to_identity(&group, sender_index)?.into());
+                let data = application_message.data().to_vec();
+                let authenticated_data = application_message.authenticated_data.to_vec();
+                Ok(ReceivedMessageFFI::ApplicationMessage {
+                    sender,
+                    sender_index,
+                    epoch: message_epoch.unwrap_or_else(|| group.current_epoch()),
+                    data,
+                    authenticated_data,
+                })
+            }
+            ReceivedMessage::Commit(commit_message) => {
+                let committer =
+                    Arc::new(index_to_identity(&group, commit_message.committer)?.into());
+                let authenticated_data = commit_message.authenticated_data.to_vec();
+                Ok(ReceivedMessageFFI::Commit {
+                    committer,
+                    effect: CommitEffectFFI::new(commit_message.effect, &group.roster()),
+                    authenticated_data,
+                })
+            }
+            ReceivedMessage::Proposal(proposal_message) => {
+                let sender = match proposal_message.sender {
+                    mls_rs::group::ProposalSender::Member(index) => ProposalSenderFFI::Member(
+                        Arc::new(index_to_identity(&group, index)?.into()),
+                    ),
+                    mls_rs::group::ProposalSender::External(index) => {
+                        ProposalSenderFFI::External { index }
+                    }
+                    mls_rs::group::ProposalSender::NewMember => {
+                        let Proposal::Add(add_proposal) = &proposal_message.proposal else {
+                            return Err(MlSrsError::UnexpectedProposalSender);
+                        };
+                        let key_package = add_proposal.key_package().clone().try_into()?;
+                        ProposalSenderFFI::NewMember {
+                            key_package: Arc::new(key_package),
+                        }
+                    }
+                };
+                let authenticated_data = proposal_message.authenticated_data.clone().to_vec();
+                let proposal_ref = proposal_message.proposal_ref.to_vec();
+                self.cache_proposal(
+                    group.current_epoch(),
+                    Some(proposal_ref.clone()),
+                    proposal_message.proposal.proposal_type(),
+                );
+                let proposal = proposal_message.try_into()?;
+                Ok(ReceivedMessageFFI::ReceivedProposal {
+                    sender,
+                    proposal,
+                    proposal_ref,
+                    authenticated_data,
+                })
+            }
+            ReceivedMessage::GroupInfo(group_info) => Ok(ReceivedMessageFFI::GroupInfo {
+                group_info: group_info.try_into()?,
+            }),
+            ReceivedMessage::Welcome => Ok(ReceivedMessageFFI::Welcome {
+                cipher_suite: cipher_suite
+                    .ok_or(MlSrsError::UnexpecteMessageFormat)?
+                    .try_into()?,
+                key_package_references,
+            }),
+            ReceivedMessage::KeyPackage(key_package) => Ok(ReceivedMessageFFI::KeyPackage {
+                key_package: Arc::new(key_package.try_into()?),
+            }),
+        }
+    }
+
+    /// Convert the output of a commit, keeping its welcome messages
+    /// while the commit is pending.
+    fn commit_output(
+        &self,
+        commit_output: mls_rs::group::CommitOutput,
+        group: &mls_rs::Group<UniFFIConfig>,
+    ) -> Result<CommitOutputFFI, MlSrsError> {
+        *self.pending_welcomes.lock().unwrap() = commit_output.welcome_messages.clone();
+        CommitOutputFFI::new(commit_output, &group.roster())
+    }
+
+    /// Find the index of the member with the same identity as
+    /// `signing_identity`, according to the identity provider.
+    ///
+    /// Members keep their identity when they rotate their signature
+    /// key, so they are found with an older signing identity too.
+    async fn identity_to_index(
+        &self,
+        group: &mls_rs::Group<UniFFIConfig>,
+        signing_identity: Arc<SigningIdentityFFI>,
+    ) -> Result<u32, MlSrsError> {
+        let extensions = Arc::new(group.context().extensions.clone().into());
+        let identifier = self
+            .identity_provider
+            .identity(signing_identity, extensions)
+            .await?;
+        Ok(group.member_with_identity(&identifier).await?.index)
+    }
+
+    fn report_commit<T, E>(&self, start: Instant, result: &Result<T, E>) {
+        self.metrics
+            .report_timed(start, result, |duration_micros, success| {
+                MetricFFI::CommitBuilt {
+                    duration_micros,
+                    success,
+                }
+            });
     }
 }
 
@@ -39,6 +326,15 @@ pub struct JoinInfo {
     pub group_info_extensions: Arc<ExtensionListFFI>,
 }
 
+/// The result of [`ClientFFI::commit_external`](crate::client::ClientFFI::commit_external).
+#[derive(uniffi::Record, Clone)]
+pub struct ExternalCommitOutputFFI {
+    /// The group that was joined.
+    pub group: Arc<GroupFFI>,
+    /// Commit message to send to the group members.
+    pub commit_message: Arc<MessageFFI>,
+}
+
 #[derive(Clone, Debug, uniffi::Record)]
 pub struct CommitOutputFFI {
     /// Commit message to send to other group members.
@@ -53,17 +349,21 @@ pub struct CommitOutputFFI {
     // pub ratchet_tree: Option<RatchetTree>,
 
     /// A group info that can be provided to new members in order to
-    /// enable external commit functionality.
+    /// enable external commit functionality. This is `None` unless
+    /// [`ClientConfigFFI::allow_external_commit`](crate::config::ClientConfigFFI::allow_external_commit)
+    /// is set.
     pub group_info: Option<Arc<MessageFFI>>,
 
     /// Proposals that were received in the prior epoch but not included in the following commit.
-    pub unused_proposals: Vec<ProposalFFI>,
+    pub unused_proposals: Vec<ProposalInfoFFI>,
 }
 
-impl TryFrom<mls_rs::group::CommitOutput> for CommitOutputFFI {
-    type Error = MlSrsError;
-
-    fn try_from(commit_output: mls_rs::group::CommitOutput) -> Result<Self, MlSrsError> {
+impl CommitOutputFFI {
+    /// Convert a commit output of a group with the given roster.
+    pub(crate) fn new(
+        commit_output: mls_rs::group::CommitOutput,
+        roster: &Roster,
+    ) -> Result<Self, MlSrsError> {
         let commit_message = Arc::new(commit_output.commit_message.into());
         let welcome_message = commit_output
             .welcome_messages
@@ -77,7 +377,7 @@ impl TryFrom<mls_rs::group::CommitOutput> for CommitOutputFFI {
             .unused_proposals
             .into_iter()
             //warning - silently fails - TODO: try_collect
-            .flat_map(|proposal_info| proposal_info.try_into())
+            .flat_map(|proposal_info| ProposalInfoFFI::new(proposal_info, roster, &[]))
             .collect();
 
         Ok(Self {
@@ -100,14 +400,42 @@ fn index_to_identity(
     Ok(member.signing_identity)
 }
 
-#[maybe_async::must_be_async]
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
 #[uniffi::export]
 impl GroupFFI {
     /// Write the current state of the group to storage defined by
     /// [`ClientConfig::group_state_storage`]
-    pub fn write_to_storage(&self) -> Result<(), MlSrsError> {
-        let mut group = self.inner();
-        group.write_to_storage().map_err(Into::into)
+    pub async fn write_to_storage(&self) -> Result<(), MlSrsError> {
+        let mut group = self.inner().await?;
+        group.write_to_storage().await.map_err(Into::into)
+    }
+
+    /// Serialize the group into a caller-managed blob.
+    ///
+    /// The blob is made from the group in memory, the
+    /// [`ClientConfigFFI::group_state_storage`](crate::config::ClientConfigFFI::group_state_storage)
+    /// of the client is not used. It contains the current epoch
+    /// only, messages of earlier epochs can't be processed by the
+    /// restored group. Restore it with
+    /// [`ClientFFI::group_from_snapshot`](crate::client::ClientFFI::group_from_snapshot).
+    ///
+    /// # Warning
+    ///
+    /// The blob contains secret key material.
+    pub async fn to_snapshot_bytes(&self) -> Result<Vec<u8>, MlSrsError> {
+        // Write a copy, so the epochs the group has not written yet
+        // still reach the client's storage.
+        let mut group = mls_rs::Group::clone(&*self.read().await?);
+        let storage: Arc<dyn GroupStateStorageProtocol> = Arc::new(GroupStateStorageAdapter::new(
+            InMemoryGroupStateStorage::new(),
+        ));
+        #[cfg(not(mls_build_async))]
+        with_group_state_storage(storage.clone(), || group.write_to_storage())?;
+        #[cfg(mls_build_async)]
+        with_group_state_storage(storage.clone(), group.write_to_storage()).await?;
+        let snapshot = GroupSnapshot::read(&*storage, group.group_id().to_vec()).await?;
+        Ok(snapshot.mls_encode_to_vec()?)
     }
 
     // /// Export the current epoch's ratchet tree in serialized format.
@@ -122,30 +450,64 @@ impl GroupFFI {
 
     /// Perform a commit of received proposals (or an empty commit).
     ///
-    /// TODO: ensure `path_required` is always set in
-    /// [`MlsRules::commit_options`](`mls_rs::MlsRules::commit_options`).
+    /// Set [`ClientConfigFFI::path_required`](crate::config::ClientConfigFFI::path_required)
+    /// to always include a path update, even if the proposals don't
+    /// require one.
     ///
     /// Returns the resulting commit message. See
     /// [`mls_rs::Group::commit`] for details.
-    pub fn commit(&self, authenticated_data: Vec<u8>) -> Result<CommitOutputFFI, MlSrsError> {
-        let mut group = self.inner();
-        let commit_output = group.commit(authenticated_data)?;
-        commit_output.try_into()
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn commit(&self, authenticated_data: Vec<u8>) -> Result<CommitOutputFFI, MlSrsError> {
+        self.mls_rules
+            .check_authenticated_data(&authenticated_data)?;
+        let mut group = self.inner().await?;
+        let start = Instant::now();
+        let commit_output = group.commit(authenticated_data).await;
+        self.report_commit(start, &commit_output);
+        self.commit_output(commit_output?, &group)
     }
 
-    pub fn commit_new_identity(
+    /// Commit only the cached proposals identified by `proposal_refs`.
+    ///
+    /// Proposals that are not referenced are left in the cache. The
+    /// references are found in
+    /// [`ReceivedMessageFFI::ReceivedProposal`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn commit_proposals(
+        &self,
+        proposal_refs: Vec<Vec<u8>>,
+        authenticated_data: Vec<u8>,
+    ) -> Result<CommitOutputFFI, MlSrsError> {
+        self.mls_rules
+            .check_authenticated_data(&authenticated_data)?;
+        let mut group = self.inner().await?;
+        let _selection = self
+            .mls_rules
+            .select_proposals(group.group_id(), proposal_refs);
+        let start = Instant::now();
+        let commit_output = group.commit(authenticated_data).await;
+        self.report_commit(start, &commit_output);
+        self.commit_output(commit_output?, &group)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn commit_new_identity(
         &self,
         signer: SignatureSecretKeyFFI,
         signing_identity: Arc<SigningIdentityFFI>,
         authenticated_data: Vec<u8>,
     ) -> Result<CommitOutputFFI, MlSrsError> {
-        let mut group = self.inner();
+        self.mls_rules
+            .check_authenticated_data(&authenticated_data)?;
+        let mut group = self.inner().await?;
         let mut commit_builder = group.commit_builder();
         commit_builder = commit_builder
             .set_new_signing_identity(signer.into(), signing_identity.inner.clone())
             .authenticated_data(authenticated_data);
-        let commit_output = commit_builder.build()?;
-        commit_output.try_into()
+        let start = Instant::now();
+        let commit_output = commit_builder.build().await;
+        self.report_commit(start, &commit_output);
+        self.commit_output(commit_output?, &group)
     }
 
     // pub fn commit_applying_proposals(&self) -> Result<CommitOutputFFI, MlSrsError> {
@@ -161,18 +523,49 @@ impl GroupFFI {
     /// The members are representated by key packages. The result is
     /// the welcome messages to send to the new members.
     ///
+    /// mls-rs takes ownership of each key package. A key package is
+    /// moved out of its [`MessageFFI`] if this holds the only
+    /// reference, otherwise it is copied, which is always the case
+    /// for foreign callers. Use [`Self::add_members_bytes`] to avoid
+    /// the copy.
+    ///
     /// See [`mls_rs::group::CommitBuilder::add_member`] for details.
-    pub fn add_members(
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn add_members(
         &self,
         key_packages: Vec<Arc<MessageFFI>>,
     ) -> Result<CommitOutputFFI, MlSrsError> {
-        let mut group = self.inner();
+        let mut group = self.inner().await?;
         let mut commit_builder = group.commit_builder();
         for key_package in key_packages {
             commit_builder = commit_builder.add_member(arc_unwrap_or_clone(key_package).inner)?;
         }
-        let commit_output = commit_builder.build()?;
-        commit_output.try_into()
+        let start = Instant::now();
+        let commit_output = commit_builder.build().await;
+        self.report_commit(start, &commit_output);
+        self.commit_output(commit_output?, &group)
+    }
+
+    /// Add members from serialized key packages.
+    ///
+    /// Equivalent to [`Self::add_members`], but the key packages are
+    /// decoded straight into the commit instead of being copied out
+    /// of [`MessageFFI`] objects.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn add_members_bytes(
+        &self,
+        key_packages: Vec<Vec<u8>>,
+    ) -> Result<CommitOutputFFI, MlSrsError> {
+        let mut group = self.inner().await?;
+        let mut commit_builder = group.commit_builder();
+        for key_package in key_packages {
+            let key_package = mls_rs::MlsMessage::from_bytes(&key_package)?;
+            commit_builder = commit_builder.add_member(key_package)?;
+        }
+        let start = Instant::now();
+        let commit_output = commit_builder.build().await;
+        self.report_commit(start, &commit_output);
+        self.commit_output(commit_output?, &group)
     }
 
     // /// Propose to add one or more members to this group.
@@ -181,86 +574,121 @@ impl GroupFFI {
     // /// the proposal messages to send to the group.
     // ///
     // /// See [`mls_rs::Group::propose_add`] for details.
-    pub fn propose_add_members(
+    pub async fn propose_add_members(
         &self,
         key_packages: Vec<Arc<MessageFFI>>,
     ) -> Result<Vec<Arc<MessageFFI>>, MlSrsError> {
-        let mut group = self.inner();
+        let mut group = self.inner().await?;
 
         let mut messages = Vec::with_capacity(key_packages.len());
         for key_package in key_packages {
-            let key_package = arc_unwrap_or_clone(key_package);
-            let message = group.propose_add(key_package.inner, Vec::new())?;
+            let message = group
+                .propose_add(arc_unwrap_or_clone(key_package).inner, Vec::new())
+                .await?;
+            self.cache_proposal(group.current_epoch(), None, ProposalType::ADD);
             messages.push(Arc::new(message.into()));
         }
         Ok(messages)
     }
 
-    pub fn propose_external_psk(
+    /// Propose to inject the external pre-shared key `psk_id`.
+    ///
+    /// See [`mls_rs::Group::propose_external_psk`] for details.
+    pub async fn propose_external_psk(
         &self,
-        psk_id: Vec<u8>,
+        psk_id: Arc<ExternalPskIdFFI>,
         authenticated_data: Vec<u8>,
     ) -> Result<MessageFFI, MlSrsError> {
-        self.inner()
-            .propose_external_psk(
-                ExternalPskId::mls_decode(&mut &*psk_id)?,
-                authenticated_data,
-            )
-            .map(Into::into)
-            .map_err(Into::into)
+        self.mls_rules
+            .check_authenticated_data(&authenticated_data)?;
+        let mut group = self.inner().await?;
+        let message = group
+            .propose_external_psk(psk_id.inner(), authenticated_data)
+            .await?;
+        self.cache_proposal(group.current_epoch(), None, ProposalType::PSK);
+        Ok(message.into())
     }
 
-    //bring this back for MultiMLS leave
+    /// Propose and commit the removal of one or more members.
+    ///
+    /// The members are representated by their signing identities.
+    /// Used by a partner to commit the proposals of a member leaving
+    /// with [`Self::propose_leave`], or to remove members directly.
+    ///
+    /// See [`mls_rs::group::CommitBuilder::remove_member`] for details.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn remove_members(
+        &self,
+        signing_identities: Vec<Arc<SigningIdentityFFI>>,
+        authenticated_data: Vec<u8>,
+    ) -> Result<CommitOutputFFI, MlSrsError> {
+        self.mls_rules
+            .check_authenticated_data(&authenticated_data)?;
+        let mut group = self.inner().await?;
 
-    // /// Propose and commit the removal of one or more members.
-    // ///
-    // /// The members are representated by their signing identities.
-    // ///
-    // /// See [`mls_rs::group::CommitBuilder::remove_member`] for details.
-    // pub async fn remove_members(
-    //     &self,
-    //     signing_identities: &[Arc<SigningIdentity>],
-    // ) -> Result<CommitOutput, MlSrsError> {
-    //     let mut group = self.inner().await;
+        // Find member indices
+        let mut member_indexes = Vec::with_capacity(signing_identities.len());
+        for signing_identity in signing_identities {
+            member_indexes.push(self.identity_to_index(&group, signing_identity).await?);
+        }
 
-    //     // Find member indices
-    //     let mut member_indixes = Vec::with_capacity(signing_identities.len());
-    //     for signing_identity in signing_identities {
-    //         let identifier = signing_identity_to_identifier(&signing_identity.inner).await?;
-    //         let member = group.member_with_identity(&identifier).await?;
-    //         member_indixes.push(member.index);
-    //     }
+        let mut commit_builder = group
+            .commit_builder()
+            .authenticated_data(authenticated_data);
+        for index in member_indexes {
+            commit_builder = commit_builder.remove_member(index)?;
+        }
+        let start = Instant::now();
+        let commit_output = commit_builder.build().await;
+        self.report_commit(start, &commit_output);
+        self.commit_output(commit_output?, &group)
+    }
 
-    //     let mut commit_builder = group.commit_builder();
-    //     for index in member_indixes {
-    //         commit_builder = commit_builder.remove_member(index)?;
-    //     }
-    //     let commit_output = commit_builder.build().await?;
-    //     commit_output.try_into()
-    // }
+    /// Propose to remove one or more members from this group.
+    ///
+    /// The members are representated by their signing identities. The
+    /// result is the proposal messages to send to the group.
+    ///
+    /// See [`mls_rs::group::Group::propose_remove`] for details.
+    pub async fn propose_remove_members(
+        &self,
+        signing_identities: Vec<Arc<SigningIdentityFFI>>,
+        authenticated_data: Vec<u8>,
+    ) -> Result<Vec<Arc<MessageFFI>>, MlSrsError> {
+        self.mls_rules
+            .check_authenticated_data(&authenticated_data)?;
+        let mut group = self.inner().await?;
 
-    // /// Propose to remove one or more members from this group.
-    // ///
-    // /// The members are representated by their signing identities. The
-    // /// result is the proposal messages to send to the group.
-    // ///
-    // /// See [`mls_rs::group::Group::propose_remove`] for details.
-    // pub async fn propose_remove_members(
-    //     &self,
-    //     signing_identities: &[Arc<SigningIdentity>],
-    // ) -> Result<Vec<Arc<Message>>, MlSrsError> {
-    //     let mut group = self.inner().await;
+        let mut messages = Vec::with_capacity(signing_identities.len());
+        for signing_identity in signing_identities {
+            let index = self.identity_to_index(&group, signing_identity).await?;
+            let message = group
+                .propose_remove(index, authenticated_data.clone())
+                .await?;
+            self.cache_proposal(group.current_epoch(), None, ProposalType::REMOVE);
+            messages.push(Arc::new(message.into()));
+        }
 
-    //     let mut messages = Vec::with_capacity(signing_identities.len());
-    //     for signing_identity in signing_identities {
-    //         let identifier = signing_identity_to_identifier(&signing_identity.inner).await?;
-    //         let member = group.member_with_identity(&identifier).await?;
-    //         let message = group.propose_remove(member.index, Vec::new()).await?;
-    //         messages.push(Arc::new(message.into()));
-    //     }
+        Ok(messages)
+    }
 
-    //     Ok(messages)
-    // }
+    /// Propose to remove ourselves from this group.
+    ///
+    /// A member can't commit its own removal, so another member has
+    /// to commit the proposal, e.g., with [`Self::commit`]. The group
+    /// should be discarded once that commit arrives.
+    pub async fn propose_leave(
+        &self,
+        authenticated_data: Vec<u8>,
+    ) -> Result<MessageFFI, MlSrsError> {
+        self.mls_rules
+            .check_authenticated_data(&authenticated_data)?;
+        let mut group = self.inner().await?;
+        let index = group.current_member_index();
+        let message = group.propose_remove(index, authenticated_data).await?;
+        self.cache_proposal(group.current_epoch(), None, ProposalType::REMOVE);
+        Ok(message.into())
+    }
 
     /// Encrypt an application message using the current group state.
     ///
@@ -273,72 +701,68 @@ impl GroupFFI {
     /// The other group members will find the message in
     /// [`ReceivedMessage::ApplicationMessage`] after calling
     /// [`Group::process_incoming_message`].
-    pub fn encrypt_application_message(
+    ///
+    /// The `padding` overrides the padding configured for the group
+    /// for this message only. It defaults to `None` in the bindings,
+    /// so existing callers keep the group's padding.
+    #[uniffi::method(default(padding = None))]
+    pub async fn encrypt_application_message(
         &self,
         message: &[u8],
         authenticated_data: Vec<u8>,
         allow_self_proposals: bool,
+        padding: Option<PaddingModeFFI>,
     ) -> Result<MessageFFI, MlSrsError> {
-        let mut group = self.inner();
-        let mls_message = group.encrypt_application_message_germ(
-            message,
-            authenticated_data,
-            allow_self_proposals,
-        )?;
+        self.mls_rules
+            .check_authenticated_data(&authenticated_data)?;
+        let mut group = self.inner().await?;
+        #[cfg(not(mls_build_async))]
+        let mls_message = with_padding(padding, || {
+            group.encrypt_application_message_germ(
+                message,
+                authenticated_data,
+                allow_self_proposals,
+            )
+        })?;
+        #[cfg(mls_build_async)]
+        let mls_message = with_padding(
+            padding,
+            group.encrypt_application_message_germ(
+                message,
+                authenticated_data,
+                allow_self_proposals,
+            ),
+        )
+        .await?;
         Ok(mls_message.into())
     }
 
     /// Process an inbound message for this group.
-    pub fn process_incoming_message(
+    ///
+    /// mls-rs takes ownership of the message. It is moved out of the
+    /// [`MessageFFI`] if this holds the only reference, otherwise it
+    /// is copied, which is always the case for foreign callers. Use
+    /// [`Self::process_incoming_message_bytes`] to avoid the copy.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn process_incoming_message(
         &self,
         message: Arc<MessageFFI>,
     ) -> Result<ReceivedMessageFFI, MlSrsError> {
-        let message = arc_unwrap_or_clone(message);
-        let mut group = self.inner();
-        match group.process_incoming_message(message.inner)? {
-            ReceivedMessage::ApplicationMessage(application_message) => {
-                let sender =
-                    Arc::new(index_to_identity(&group, application_message.sender_index)?.into());
-                let data = application_message.data().to_vec();
-                let authenticated_data = application_message.authenticated_data.to_vec();
-                Ok(ReceivedMessageFFI::ApplicationMessage {
-                    sender,
-                    data,
-                    authenticated_data,
-                })
-            }
-            ReceivedMessage::Commit(commit_message) => {
-                let committer =
-                    Arc::new(index_to_identity(&group, commit_message.committer)?.into());
-                let authenticated_data = commit_message.authenticated_data.to_vec();
-                Ok(ReceivedMessageFFI::Commit {
-                    committer,
-                    effect: commit_message.effect.into(),
-                    authenticated_data,
-                })
-            }
-            ReceivedMessage::Proposal(proposal_message) => {
-                let sender = match proposal_message.sender {
-                    mls_rs::group::ProposalSender::Member(index) => {
-                        Arc::new(index_to_identity(&group, index)?.into())
-                    }
-                    _ => todo!("External and NewMember proposal senders are not supported"),
-                };
-                let authenticated_data = proposal_message.authenticated_data.clone().to_vec();
-                let proposal = proposal_message.try_into()?;
-                Ok(ReceivedMessageFFI::ReceivedProposal {
-                    sender,
-                    proposal,
-                    authenticated_data,
-                })
-            }
-            // TODO: group::ReceivedMessage::GroupInfo does not have any
-            // public methods (unless the "ffi" Cargo feature is set).
-            // So perhaps we don't need it?
-            ReceivedMessage::GroupInfo(_) => Ok(ReceivedMessageFFI::GroupInfo),
-            ReceivedMessage::Welcome => Ok(ReceivedMessageFFI::Welcome),
-            ReceivedMessage::KeyPackage(_) => Ok(ReceivedMessageFFI::KeyPackage),
-        }
+        self.process_message(arc_unwrap_or_clone(message).inner)
+            .await
+    }
+
+    /// Parse and process a serialized message.
+    ///
+    /// Equivalent to [`Self::process_incoming_message`] without
+    /// creating a [`MessageFFI`] first.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn process_incoming_message_bytes(
+        &self,
+        message: Vec<u8>,
+    ) -> Result<ReceivedMessageFFI, MlSrsError> {
+        let message = mls_rs::MlsMessage::from_bytes(&message)?;
+        self.process_message(message).await
     }
 
     // //MARK: Germ helpers
@@ -347,63 +771,249 @@ impl GroupFFI {
     // /// The indexes within this roster do not correlate with indexes of users
     // /// within [`ReceivedMessage`] content descriptions due to the layout of
     // /// member information within a MLS group state.
-    pub fn members(&self) -> Vec<Arc<MLSMemberFFI>> {
+    pub async fn members(&self) -> Result<Vec<Arc<MLSMemberFFI>>, MlSrsError> {
         // let group = self.inner().await;
-        self.inner()
+        Ok(self
+            .read()
+            .await?
             .roster()
             .members()
             .iter()
             .map(|member| Arc::new(member.clone().into()))
-            .collect()
+            .collect())
+    }
+
+    /// Return up to `limit` members starting at position `offset` in
+    /// the roster.
+    ///
+    /// The same warning as for [`GroupFFI::members`] applies: the
+    /// position in the roster is not the member index.
+    pub async fn members_page(
+        &self,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<Arc<MLSMemberFFI>>, MlSrsError> {
+        let group = self.read().await?;
+        Ok(self
+            .roster_indices(&group)
+            .iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .filter_map(|&index| group.member_at_index(index))
+            .map(|member| Arc::new(member.into()))
+            .collect())
+    }
+
+    /// A handle to read the roster without copying it, see
+    /// [`RosterFFI`].
+    pub async fn roster(&self) -> Arc<RosterFFI> {
+        Arc::new(RosterFFI {
+            group: self.clone(),
+        })
     }
 
-    pub fn group_id(&self) -> Vec<u8> {
-        self.inner().group_id().to_vec()
+    /// Number of members in the group.
+    pub async fn member_count(&self) -> Result<u32, MlSrsError> {
+        let group = self.read().await?;
+        Ok(self.roster_indices(&group).len() as u32)
     }
 
-    pub fn current_epoch(&self) -> u64 {
-        self.inner().current_epoch()
+    pub async fn group_id(&self) -> Result<Vec<u8>, MlSrsError> {
+        Ok(self.read().await?.group_id().to_vec())
     }
 
-    pub fn current_member_index(&self) -> u32 {
-        self.inner().current_member_index()
+    pub async fn current_epoch(&self) -> Result<u64, MlSrsError> {
+        Ok(self.read().await?.current_epoch())
+    }
+
+    pub async fn current_member_index(&self) -> Result<u32, MlSrsError> {
+        Ok(self.read().await?.current_member_index())
+    }
+
+    /// Tree hash of the current epoch.
+    ///
+    /// Members in the same state have the same tree hash, so it can
+    /// be compared out of band without exporting a secret.
+    pub async fn tree_hash(&self) -> Result<Vec<u8>, MlSrsError> {
+        Ok(self.read().await?.context().tree_hash.clone())
+    }
+
+    /// Confirmed transcript hash of the current epoch.
+    pub async fn confirmed_transcript_hash(&self) -> Result<Vec<u8>, MlSrsError> {
+        Ok(self
+            .read()
+            .await?
+            .context()
+            .confirmed_transcript_hash
+            .to_vec())
+    }
+
+    /// A signed GroupInfo of the current epoch, e.g., for a new
+    /// device of an existing member.
+    ///
+    /// With `allow_external_commit` the GroupInfo includes the
+    /// external public key, which lets its holder join with an
+    /// external commit.
+    pub async fn group_info_message(
+        &self,
+        allow_external_commit: bool,
+    ) -> Result<MessageFFI, MlSrsError> {
+        let group = self.read().await?;
+        Ok(group
+            .group_info_message(allow_external_commit)
+            .await?
+            .into())
+    }
+
+    /// Extensions of the current group context, e.g., a policy
+    /// extension committed earlier.
+    pub async fn group_context_extensions(&self) -> Result<Arc<ExtensionListFFI>, MlSrsError> {
+        Ok(Arc::new(
+            self.read().await?.context().extensions.clone().into(),
+        ))
+    }
+
+    /// A digest of the current group state to compare with other
+    /// members, see [`Self::compare_digest`].
+    pub async fn state_digest(&self) -> Result<StateDigestFFI, MlSrsError> {
+        let group = self.read().await?;
+        let context = group.context();
+        Ok(StateDigestFFI {
+            epoch: context.epoch,
+            tree_hash: context.tree_hash.clone(),
+            confirmed_transcript_hash: context.confirmed_transcript_hash.to_vec(),
+        })
+    }
+
+    /// Compare our state with the [`Self::state_digest`] of another
+    /// member to detect a fork before messages start failing.
+    pub async fn compare_digest(
+        &self,
+        other: StateDigestFFI,
+    ) -> Result<StateDivergenceFFI, MlSrsError> {
+        Ok(self.state_digest().await?.compare(&other))
+    }
+
+    /// Describe the group for attaching to bug reports.
+    ///
+    /// The description contains no secret material.
+    pub async fn debug_dump(&self) -> Result<GroupDebugInfoFFI, MlSrsError> {
+        let group = self.read().await?;
+        let context = group.context();
+        let pending_proposals = self
+            .cached_proposals
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(epoch, _)| *epoch == context.epoch)
+            .map(|(_, proposal)| proposal.clone())
+            .collect();
+        Ok(GroupDebugInfoFFI {
+            group_id: context.group_id.clone(),
+            epoch: context.epoch,
+            protocol_version: context.protocol_version.into(),
+            cipher_suite: context.cipher_suite.into(),
+            current_member_index: group.current_member_index(),
+            members: group
+                .roster()
+                .members()
+                .into_iter()
+                .map(|member| Arc::new(member.into()))
+                .collect(),
+            has_pending_commit: group.has_pending_commit(),
+            pending_proposals,
+            extension_types: context
+                .extensions
+                .iter()
+                .map(|extension| extension.extension_type.raw_value())
+                .collect(),
+        })
     }
 
     //for proposing in my own group
-    pub fn propose_update(
+    pub async fn propose_update(
         &self,
         signer: Option<SignatureSecretKeyFFI>,
         signing_identity: Option<Arc<SigningIdentityFFI>>,
         authenticated_data: Vec<u8>,
     ) -> Result<MessageFFI, MlSrsError> {
-        let mut group = self.inner();
+        self.mls_rules
+            .check_authenticated_data(&authenticated_data)?;
+        let mut group = self.inner().await?;
 
-        match (signer, signing_identity) {
+        let message = match (signer, signing_identity) {
             (Some(signer), Some(signing_identity)) => {
-                let message = group.propose_update_with_identity(
-                    signer.into(),
-                    arc_unwrap_or_clone(signing_identity).inner,
-                    authenticated_data,
-                );
-                Ok(message?.into())
+                group
+                    .propose_update_with_identity(
+                        signer.into(),
+                        arc_unwrap_or_clone(signing_identity).inner,
+                        authenticated_data,
+                    )
+                    .await?
             }
-            (None, None) => Ok(group.propose_update(authenticated_data)?.into()),
-            _ => Err(MlSrsError::InconsistentOptionalParameters),
+            (None, None) => group.propose_update(authenticated_data).await?,
+            _ => return Err(MlSrsError::InconsistentOptionalParameters),
+        };
+        self.cache_proposal(group.current_epoch(), None, ProposalType::UPDATE);
+        Ok(message.into())
+    }
+
+    /// Welcome messages of our pending commit, to send again if they
+    /// were lost.
+    ///
+    /// mls-rs can't recreate a welcome, so the messages are kept in
+    /// memory from the commit call. The list is empty once the commit
+    /// is applied or cleared, and after the group is loaded again.
+    pub async fn pending_welcome_messages(&self) -> Result<Vec<Arc<MessageFFI>>, MlSrsError> {
+        let group = self.read().await?;
+        if !group.has_pending_commit() {
+            return Ok(Vec::new());
         }
+        Ok(self
+            .pending_welcomes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|welcome_message| Arc::new(welcome_message.clone().into()))
+            .collect())
+    }
+
+    pub async fn clear_proposal_cache(&self) -> Result<(), MlSrsError> {
+        self.inner().await?.clear_proposal_cache();
+        self.cached_proposals.lock().unwrap().clear();
+        Ok(())
     }
 
-    pub fn clear_proposal_cache(&self) {
-        self.inner().clear_proposal_cache()
+    /// Discard a cached proposal, so our commits in the current epoch
+    /// no longer include it.
+    ///
+    /// The reference is found in
+    /// [`ReceivedMessageFFI::ReceivedProposal`]. mls-rs has no way to
+    /// remove a single proposal from its cache, so the proposal is
+    /// filtered out of our commits instead: it is still reported in
+    /// [`CommitOutputFFI::unused_proposals`] and a commit from another
+    /// member may still include it. The filter is dropped with the
+    /// next commit, when the whole proposal cache is discarded.
+    pub async fn discard_proposal(&self, proposal_ref: Vec<u8>) -> Result<(), MlSrsError> {
+        let group = self.read().await?;
+        self.mls_rules
+            .exclude_proposal(group.group_id(), group.current_epoch(), proposal_ref);
+        Ok(())
     }
 
     // pub async fn proposal_cache_is_empty(&self) -> bool {
     //     self.inner().await.proposal_cache_is_empty()
     // }
 
-    pub fn member_at_index(&self, index: u32) -> Option<Arc<MLSMemberFFI>> {
-        self.inner()
+    pub async fn member_at_index(
+        &self,
+        index: u32,
+    ) -> Result<Option<Arc<MLSMemberFFI>>, MlSrsError> {
+        Ok(self
+            .read()
+            .await?
             .member_at_index(index)
-            .map(|message| Arc::new(message.into()))
+            .map(|message| Arc::new(message.into())))
     }
 
     // //Propose replace from update
@@ -465,18 +1075,154 @@ impl GroupFFI {
     //     }
     // }
 
-    pub fn export_secret(
+    /// Export a secret of `len` bytes from the current epoch.
+    ///
+    /// See [`mls_rs::Group::export_secret`] for details.
+    pub async fn export_secret(
         &self,
         label: Vec<u8>,
         context: Vec<u8>,
         len: u64,
-    ) -> Result<Vec<u8>, MlSrsError> {
-        let result = self
-            .inner()
-            .export_secret(&label, &context, len as usize)?
-            .as_bytes()
-            .to_vec();
-        Ok(result)
+    ) -> Result<Arc<SecretFFI>, MlSrsError> {
+        let group = self.read().await?;
+        let secret = group.export_secret(&label, &context, len as usize).await?;
+        let cipher_suite = group.cipher_suite().try_into()?;
+        Ok(Arc::new(SecretFFI::new(
+            cipher_suite,
+            provider_cipher_suite_provider(Some(self.crypto_provider.clone()), cipher_suite)?,
+            Zeroizing::new(secret.as_bytes().to_vec()),
+        )))
+    }
+}
+
+/// See [`GroupFFI::state_digest`].
+#[derive(Clone, Debug, PartialEq, Eq, uniffi::Record)]
+pub struct StateDigestFFI {
+    pub epoch: u64,
+    pub tree_hash: Vec<u8>,
+    pub confirmed_transcript_hash: Vec<u8>,
+}
+
+impl StateDigestFFI {
+    fn compare(&self, other: &Self) -> StateDivergenceFFI {
+        if self.epoch != other.epoch {
+            return StateDivergenceFFI::EpochMismatch {
+                local_epoch: self.epoch,
+                remote_epoch: other.epoch,
+            };
+        }
+        let tree_hash_matches = self.tree_hash == other.tree_hash;
+        let transcript_hash_matches =
+            self.confirmed_transcript_hash == other.confirmed_transcript_hash;
+        match tree_hash_matches && transcript_hash_matches {
+            true => StateDivergenceFFI::InSync,
+            false => StateDivergenceFFI::Forked {
+                tree_hash_matches,
+                transcript_hash_matches,
+            },
+        }
+    }
+}
+
+/// The result of [`GroupFFI::compare_digest`].
+#[derive(Clone, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum StateDivergenceFFI {
+    /// Both members are in the same state.
+    InSync,
+    /// The members are in different epochs, so one of them has not
+    /// processed all commits yet.
+    EpochMismatch { local_epoch: u64, remote_epoch: u64 },
+    /// The members are in the same epoch with different states, so
+    /// the group has forked.
+    Forked {
+        tree_hash_matches: bool,
+        transcript_hash_matches: bool,
+    },
+}
+
+/// A description of a group, see [`GroupFFI::debug_dump`].
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct GroupDebugInfoFFI {
+    pub group_id: Vec<u8>,
+    pub epoch: u64,
+    pub protocol_version: u16,
+    pub cipher_suite: u16,
+    pub current_member_index: u32,
+    pub members: Vec<Arc<MLSMemberFFI>>,
+    /// Whether a commit created by this member is waiting to be
+    /// processed.
+    pub has_pending_commit: bool,
+    /// Proposals in the proposal cache of the current epoch.
+    ///
+    /// mls-rs does not expose its proposal cache, so the proposals
+    /// are recorded as this handle creates and receives them.
+    /// Proposals cached before the group was loaded are missing.
+    pub pending_proposals: Vec<PendingProposalFFI>,
+    /// Types of the group context extensions.
+    pub extension_types: Vec<u16>,
+}
+
+/// A cached proposal, see [`GroupDebugInfoFFI::pending_proposals`].
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct PendingProposalFFI {
+    /// The proposal reference, `None` for our own proposals since
+    /// mls-rs does not return their references.
+    pub proposal_ref: Option<Vec<u8>>,
+    pub proposal_type: u16,
+}
+
+/// A handle to the roster of a group, see [`GroupFFI::roster`].
+///
+/// Members are read from the group on each call instead of being
+/// copied up front, so the roster reflects the current epoch of the
+/// group.
+#[derive(Clone, uniffi::Object)]
+pub struct RosterFFI {
+    group: GroupFFI,
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl RosterFFI {
+    async fn member(&self, position: u32) -> Result<Option<mls_rs::group::Member>, MlSrsError> {
+        let group = self.group.read().await?;
+        let indices = self.group.roster_indices(&group);
+        Ok(indices
+            .get(position as usize)
+            .and_then(|&index| group.member_at_index(index)))
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+impl RosterFFI {
+    /// Number of members in the group.
+    pub async fn count(&self) -> Result<u32, MlSrsError> {
+        self.group.member_count().await
+    }
+
+    /// The member at `position` in the roster.
+    ///
+    /// The same warning as for [`GroupFFI::members`] applies: the
+    /// position in the roster is not the member index.
+    pub async fn member_at(&self, position: u32) -> Result<Option<Arc<MLSMemberFFI>>, MlSrsError> {
+        Ok(self
+            .member(position)
+            .await?
+            .map(|member| Arc::new(member.into())))
+    }
+
+    /// The signing identity of the member at `position` in the
+    /// roster.
+    pub async fn signing_identity_at(
+        &self,
+        position: u32,
+    ) -> Result<Option<Arc<SigningIdentityFFI>>, MlSrsError> {
+        Ok(self
+            .member(position)
+            .await?
+            .map(|member| Arc::new(member.signing_identity.into())))
     }
 }
 
@@ -486,6 +1232,8 @@ pub struct MLSMemberFFI {
     pub index: u32,
     /// Current identity public key and credential of this member.
     pub signing_identity: Arc<SigningIdentityFFI>,
+    pub(crate) extensions: mls_rs::ExtensionList,
+    pub(crate) capabilities: CapabilitiesFFI,
 }
 
 #[uniffi::export]
@@ -497,13 +1245,36 @@ impl MLSMemberFFI {
     pub fn get_signing_identity(&self) -> Arc<SigningIdentityFFI> {
         self.signing_identity.clone()
     }
+
+    /// Extensions of the member's leaf node, e.g., application data
+    /// such as an application id.
+    pub fn get_extensions(&self) -> Arc<ExtensionListFFI> {
+        Arc::new(self.extensions.clone().into())
+    }
+
+    /// Capabilities advertised by the member's leaf node.
+    pub fn get_capabilities(&self) -> CapabilitiesFFI {
+        self.capabilities.clone()
+    }
+
+    /// Whether the member supports the extension type
+    /// `extension_type`.
+    ///
+    /// The default extension types are supported by every member
+    /// and not listed in the capabilities.
+    pub fn supports_extension(&self, extension_type: u16) -> bool {
+        ExtensionType::new(extension_type).is_default()
+            || self.capabilities.extensions.contains(&extension_type)
+    }
 }
 
 impl From<mls_rs::group::Member> for MLSMemberFFI {
     fn from(inner: mls_rs::group::Member) -> Self {
         Self {
             index: inner.index,
-            signing_identity: Arc::new(inner.signing_identity.clone().into()),
+            signing_identity: Arc::new(inner.signing_identity.into()),
+            extensions: inner.extensions,
+            capabilities: (&inner.capabilities).into(),
         }
     }
 }
@@ -19,9 +19,14 @@
 
 pub mod client;
 pub mod config;
+pub mod crypto;
+pub mod external_client;
 pub mod group;
 pub mod message;
 pub mod mls_rs_error;
+pub mod session;
+#[cfg(feature = "tracing")]
+pub mod span_logger;
 
 use crate::config::group_context::ExtensionListFFI;
 use crate::mls_rs_error::MlSrsError;
@@ -37,6 +42,14 @@ pub enum ProtocolVersion {
     Mls10,
 }
 
+impl From<ProtocolVersion> for mls_rs::ProtocolVersion {
+    fn from(version: ProtocolVersion) -> mls_rs::ProtocolVersion {
+        match version {
+            ProtocolVersion::Mls10 => mls_rs::ProtocolVersion::MLS_10,
+        }
+    }
+}
+
 impl TryFrom<mls_rs::ProtocolVersion> for ProtocolVersion {
     type Error = MlSrsError;
 
@@ -58,15 +71,33 @@ fn arc_unwrap_or_clone<T: Clone>(arc: Arc<T>) -> T {
     }
 }
 
-#[cfg(test)]
+// The tests call the sync API.
+#[cfg(all(test, not(mls_build_async)))]
 mod tests {
     use super::*;
-    use crate::client::{generate_signature_keypair, ClientFFI};
+    use crate::client::{
+        generate_signature_keypair, sign, signature_keypair_from_seed, supported_cipher_suites,
+        verify, ClientFFI, ClientMessageFFI,
+    };
+    use crate::config::crypto_provider::SigningKeyResolverProtocol;
+    use crate::config::encrypted_storage::{encrypted_group_state_storage, StorageKeyProtocol};
+    use crate::config::file_storage::FileStorage;
     use crate::config::group_context::CipherSuiteFFI;
-    use crate::config::group_state::{EpochRecordFFI, GroupStateStorageProtocol};
-    use crate::config::ClientConfigFFI;
+    use crate::config::group_state::{
+        EpochRecordFFI, GroupStateStorageProtocol, KeyPackageDataFFI, KeyPackageStorageProtocol,
+    };
+    use crate::config::mls_rules::PaddingModeFFI;
+    #[cfg(feature = "sqlite")]
+    use crate::config::sqlite::SqliteGroupStateStorage;
+    use crate::config::{
+        ClientConfigFFI, KeyPackagePolicyFFI, SignatureKeypairFFI, SignaturePublicKeyFFI,
+        SignatureSecretKeyFFI, SigningIdentityFFI,
+    };
+    use crate::crypto::{generate_hpke_keypair, hpke_open, hpke_seal, random_bytes};
+    use crate::external_client::ExternalClientFFI;
     use crate::group::GroupFFI;
-    use crate::message::ReceivedMessageFFI;
+    use crate::message::{MessageFFI, ReceivedMessageFFI};
+    use crate::session::{LeaveProposalFFI, SessionFFI};
     use mls_rs_core::group::EpochRecord;
     use std::collections::HashMap;
     use std::sync::Mutex;
@@ -74,13 +105,16 @@ mod tests {
     #[test]
     fn test_simple_scenario() -> Result<(), MlSrsError> {
         let (alice_group, bob_group) = setup_test()?;
-        let message = alice_group.encrypt_application_message(b"hello, bob", vec![], false)?;
+        let message =
+            alice_group.encrypt_application_message(b"hello, bob", vec![], false, None)?;
         let received_message = bob_group.process_incoming_message(Arc::new(message))?;
 
         alice_group.write_to_storage()?;
 
         let ReceivedMessageFFI::ApplicationMessage {
             sender: _,
+            sender_index,
+            epoch,
             data,
             authenticated_data: _,
         } = received_message
@@ -88,6 +122,8 @@ mod tests {
             panic!("Wrong message type: {received_message:?}");
         };
         assert_eq!(data, b"hello, bob");
+        assert_eq!(sender_index, alice_group.current_member_index()?);
+        assert_eq!(epoch, alice_group.current_epoch()?);
 
         Ok(())
     }
@@ -96,13 +132,16 @@ mod tests {
     fn test_germ_scenario() -> Result<(), MlSrsError> {
         let (alice_group, bob_group) = setup_test()?;
 
-        let message = alice_group.encrypt_application_message(b"hello, bob", vec![], false)?;
+        let message =
+            alice_group.encrypt_application_message(b"hello, bob", vec![], false, None)?;
         let received_message = bob_group.process_incoming_message(Arc::new(message))?;
 
         alice_group.write_to_storage()?;
 
         let ReceivedMessageFFI::ApplicationMessage {
             sender: _,
+            sender_index: _,
+            epoch: _,
             data,
             authenticated_data: _,
         } = received_message
@@ -110,13 +149,13 @@ mod tests {
             panic!("Wrong message type: {received_message:?}");
         };
         assert_eq!(data, b"hello, bob");
-        assert_eq!(alice_group.current_epoch(), 1);
-        assert_eq!(bob_group.current_epoch(), 1);
+        assert_eq!(alice_group.current_epoch()?, 1);
+        assert_eq!(bob_group.current_epoch()?, 1);
 
-        assert_eq!(alice_group.current_member_index(), 0);
-        assert_eq!(bob_group.current_member_index(), 1);
+        assert_eq!(alice_group.current_member_index()?, 0);
+        assert_eq!(bob_group.current_member_index()?, 1);
 
-        assert_eq!(alice_group.group_id(), bob_group.group_id());
+        assert_eq!(alice_group.group_id()?, bob_group.group_id()?);
 
         //adding on additional germ steps here
         let update = bob_group.propose_update(None, None, vec![])?;
@@ -132,6 +171,7 @@ mod tests {
             b"hello, alice",
             commit_output.commit_message.to_bytes()?,
             false,
+            None,
         )?;
 
         let extracted_commit_maybe = next_message.unchecked_auth_data_message(
@@ -148,6 +188,8 @@ mod tests {
 
         let ReceivedMessageFFI::ApplicationMessage {
             sender: _,
+            sender_index: _,
+            epoch: _,
             data: next_data,
             authenticated_data: _,
         } = received
@@ -193,6 +235,7 @@ mod tests {
             b"hello, bob",
             update.inner.to_bytes()?,
             true,
+            None,
         )?;
 
         let _inner_combined = message.unchecked_auth_data_message(
@@ -203,6 +246,29 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_padding_override() -> Result<(), MlSrsError> {
+        let (alice_group, bob_group) = setup_test()?;
+        let padded = alice_group.encrypt_application_message(b"hi", vec![], false, None)?;
+        let unpadded = alice_group.encrypt_application_message(
+            b"hi",
+            vec![],
+            false,
+            Some(PaddingModeFFI::None),
+        )?;
+        assert!(unpadded.to_bytes()?.len() < padded.to_bytes()?.len());
+
+        // The override only applies to its own message.
+        let padded_again = alice_group.encrypt_application_message(b"hi", vec![], false, None)?;
+        assert_eq!(padded_again.to_bytes()?.len(), padded.to_bytes()?.len());
+
+        for message in [padded, unpadded, padded_again] {
+            bob_group.process_incoming_message(Arc::new(message))?;
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_propose_then_encrypt() -> Result<(), MlSrsError> {
         let (alice_group, _bob_group) = setup_test()?;
@@ -213,28 +279,711 @@ mod tests {
             b"hello, bob",
             alice_update.inner.to_bytes()?,
             true,
+            None,
         )?;
 
         Ok(())
     }
 
-    fn setup_test() -> Result<(GroupFFI, GroupFFI), MlSrsError> {
+    #[test]
+    fn test_discard_proposal() -> Result<(), MlSrsError> {
+        let (alice_group, bob_group) = setup_test()?;
+        let bob_update = bob_group.propose_update(None, None, vec![])?;
+        let ReceivedMessageFFI::ReceivedProposal { proposal_ref, .. } =
+            alice_group.process_incoming_message(Arc::new(bob_update))?
+        else {
+            panic!("expected a proposal");
+        };
+
+        alice_group.discard_proposal(proposal_ref)?;
+        let commit = alice_group.commit(Vec::new())?;
+        assert_eq!(commit.unused_proposals.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() -> Result<(), MlSrsError> {
+        let (alice_group, bob_group) = setup_test()?;
+        let snapshot = bob_group.to_snapshot_bytes()?;
+
+        let keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let bob_extension = ClientFFI::new(b"bob".to_vec(), keypair, ClientConfigFFI::default());
+        let restored_group = bob_extension.group_from_snapshot(snapshot)?;
+        assert_eq!(restored_group.current_epoch()?, bob_group.current_epoch()?);
+
+        let message =
+            alice_group.encrypt_application_message(b"hello, bob", vec![], false, None)?;
+        let received_message = restored_group.process_incoming_message(Arc::new(message))?;
+
+        let ReceivedMessageFFI::ApplicationMessage { data, .. } = received_message else {
+            panic!("Wrong message type: {received_message:?}");
+        };
+        assert_eq!(data, b"hello, bob");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_any_message() -> Result<(), MlSrsError> {
+        let alice_keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let alice = ClientFFI::new(b"alice".to_vec(), alice_keypair, ClientConfigFFI::default());
+        let bob_keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let bob = ClientFFI::new(b"bob".to_vec(), bob_keypair, ClientConfigFFI::default());
+
+        let key_package = Arc::new(bob.generate_key_package_message(None, None)?);
+        let classified = alice.process_any_message(key_package.clone())?;
+        assert!(matches!(classified, ClientMessageFFI::KeyPackage { .. }));
+
+        let alice_group = alice.create_group(None)?;
+        let commit = alice_group.add_members(vec![key_package])?;
+        let classified = bob.process_any_message(commit.welcome_message.unwrap())?;
+        assert!(matches!(classified, ClientMessageFFI::Welcome { .. }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_dump_pending_proposals() -> Result<(), MlSrsError> {
+        let (alice_group, bob_group) = setup_test()?;
+
+        let proposal = bob_group.propose_update(None, None, Vec::new())?;
+        let ReceivedMessageFFI::ReceivedProposal { proposal_ref, .. } =
+            alice_group.process_incoming_message(Arc::new(proposal))?
+        else {
+            panic!("Expected a proposal");
+        };
+
+        let pending_proposals = alice_group.debug_dump()?.pending_proposals;
+        assert_eq!(pending_proposals.len(), 1);
+        assert_eq!(pending_proposals[0].proposal_ref, Some(proposal_ref));
+        assert_eq!(bob_group.debug_dump()?.pending_proposals.len(), 1);
+
+        // Committing the proposal empties the cache.
+        let commit = alice_group.commit(Vec::new())?;
+        bob_group.process_incoming_message(commit.commit_message.clone())?;
+        alice_group.process_incoming_message(commit.commit_message)?;
+        assert!(alice_group.debug_dump()?.pending_proposals.is_empty());
+        assert!(bob_group.debug_dump()?.pending_proposals.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_propose_leave() -> Result<(), MlSrsError> {
+        let keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let alice = Arc::new(ClientFFI::new(
+            b"alice".to_vec(),
+            keypair,
+            ClientConfigFFI::default(),
+        ));
+        let session = SessionFFI::new(alice.clone());
+        let group = Arc::new(alice.create_group(None)?);
+        session.insert_group(group.clone())?;
+
+        // The missing group doesn't prevent the proposal for the other.
+        let proposals =
+            session.propose_leave(vec![group.group_id()?, b"missing".to_vec()], Vec::new());
+        assert!(matches!(
+            &proposals[..],
+            [
+                LeaveProposalFFI::Proposed { .. },
+                LeaveProposalFFI::Failed { .. }
+            ]
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_key_package() -> Result<(), MlSrsError> {
+        let keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let carol = ClientFFI::new(b"carol".to_vec(), keypair, ClientConfigFFI::default());
+        let key_package = carol.generate_key_package_message(None, None)?;
+        let external_client =
+            ExternalClientFFI::new(ClientConfigFFI::default().identity_provider_storage);
+
+        let validated =
+            external_client.validate_key_package(Arc::new(key_package.clone()), None)?;
+        assert_eq!(
+            validated.get_leaf_node_signing_identity().inner,
+            carol.signing_identity()?.inner
+        );
+
+        // The key package signature is encoded last.
+        let mut tampered = key_package.to_bytes()?;
+        *tampered.last_mut().unwrap() ^= 1;
+        let tampered = MessageFFI::new(&tampered)?;
+        assert!(external_client
+            .validate_key_package(Arc::new(tampered), None)
+            .is_err());
+
+        let expired = external_client.validate_key_package(Arc::new(key_package), Some(0));
+        assert!(matches!(expired, Err(MlSrsError::KeyPackageExpired)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_member_after_key_rotation() -> Result<(), MlSrsError> {
+        let (alice_group, bob_group) = setup_test()?;
+        let old_identity = bob_group
+            .member_at_index(bob_group.current_member_index()?)?
+            .expect("bob is a member")
+            .get_signing_identity();
+
+        let keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let new_identity = SigningIdentityFFI::new(keypair.public_key.bytes, b"bob".to_vec())?;
+        let commit =
+            bob_group.commit_new_identity(keypair.secret_key, Arc::new(new_identity), vec![])?;
+        bob_group.process_incoming_message(commit.commit_message.clone())?;
+        alice_group.process_incoming_message(commit.commit_message)?;
+
+        let commit = alice_group.remove_members(vec![old_identity], vec![])?;
+        alice_group.process_incoming_message(commit.commit_message)?;
+        assert_eq!(alice_group.member_count()?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_roster() -> Result<(), MlSrsError> {
+        let (alice_group, bob_group) = setup_test()?;
+        let roster = alice_group.roster();
+        let members = alice_group.members()?;
+
+        assert_eq!(roster.count()?, 2);
+        for (position, member) in members.iter().enumerate() {
+            let roster_member = roster.member_at(position as u32)?.expect("member");
+            assert_eq!(roster_member.index, member.index);
+        }
+        assert!(roster.member_at(2)?.is_none());
+        assert_eq!(alice_group.members_page(1, 5)?.len(), 1);
+
+        // The roster follows the group into the next epoch.
+        let bob = bob_group
+            .member_at_index(bob_group.current_member_index()?)?
+            .expect("bob is a member")
+            .get_signing_identity();
+        let commit = alice_group.remove_members(vec![bob], vec![])?;
+        alice_group.process_incoming_message(commit.commit_message)?;
+        assert_eq!(roster.count()?, 1);
+        assert!(roster.signing_identity_at(1)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wipe() -> Result<(), MlSrsError> {
+        let storage = Arc::new(CustomGroupStateStorage::new());
+        let keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let config = ClientConfigFFI {
+            group_state_storage: storage.clone(),
+            ..Default::default()
+        };
+        let alice = ClientFFI::new(b"alice".to_vec(), keypair, config);
+        let group = alice.create_group(None)?;
+        group.write_to_storage()?;
+        let group_id = group.group_id()?;
+
+        alice.wipe()?;
+        assert!(storage.lock().is_empty());
+        assert!(matches!(group.current_epoch(), Err(MlSrsError::Wiped)));
+        assert!(matches!(group.commit(Vec::new()), Err(MlSrsError::Wiped)));
+        assert!(matches!(alice.load_group(group_id), Err(MlSrsError::Wiped)));
+        assert!(matches!(
+            alice.generate_key_package_message(None, None),
+            Err(MlSrsError::Wiped)
+        ));
+        alice.wipe()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_namespace() -> Result<(), MlSrsError> {
+        let storage = Arc::new(CustomGroupStateStorage::new());
+        let client = |namespace: &[u8]| -> Result<ClientFFI, MlSrsError> {
+            let keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+            let config = ClientConfigFFI {
+                group_state_storage: storage.clone(),
+                storage_namespace: Some(namespace.to_vec()),
+                ..Default::default()
+            };
+            Ok(ClientFFI::new(b"alice".to_vec(), keypair, config))
+        };
+        let acct1 = client(b"acct1")?;
+        let acct10 = client(b"acct10")?;
+        acct1.create_group(None)?.write_to_storage()?;
+        acct10.create_group(None)?.write_to_storage()?;
+        assert_eq!(storage.lock().len(), 2);
+
+        // Wiping acct1 leaves the group of acct10 alone.
+        acct1.wipe()?;
+        assert_eq!(storage.lock().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_stored_epochs() -> Result<(), MlSrsError> {
+        fn stored_epochs(
+            max_stored_epochs: Option<u32>,
+            max_past_epochs: Option<u32>,
+        ) -> Result<usize, MlSrsError> {
+            let storage = Arc::new(CustomGroupStateStorage::new());
+            let keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+            let config = ClientConfigFFI {
+                group_state_storage: storage.clone(),
+                max_stored_epochs,
+                max_past_epochs,
+                ..Default::default()
+            };
+            let alice = ClientFFI::new(b"alice".to_vec(), keypair, config);
+            let group = alice.create_group(None)?;
+            for _ in 0..5 {
+                let commit = group.commit(Vec::new())?;
+                group.process_incoming_message(commit.commit_message)?;
+                group.write_to_storage()?;
+            }
+            let groups = storage.lock();
+            Ok(groups.values().map(|group| group.epoch_data.len()).sum())
+        }
+
+        assert_eq!(stored_epochs(Some(2), None)?, 2);
+        assert_eq!(stored_epochs(Some(2), Some(3))?, 4);
+        assert!(stored_epochs(None, None)? > 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_callback_error_round_trip() -> Result<(), MlSrsError> {
+        #[derive(Debug)]
+        struct MissingKey;
+
+        impl StorageKeyProtocol for MissingKey {
+            fn key(&self, _group_id: Vec<u8>) -> Result<Vec<u8>, MlSrsError> {
+                Err(MlSrsError::CallbackError {
+                    domain: "keychain".to_string(),
+                    code: -25300,
+                    message: "item not found".to_string(),
+                })
+            }
+        }
+
+        let storage = encrypted_group_state_storage(
+            Arc::new(CustomGroupStateStorage::new()),
+            CipherSuiteFFI::Curve25519ChaCha,
+            Arc::new(MissingKey),
+            None,
+        )?;
+        let keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let config = ClientConfigFFI {
+            group_state_storage: storage,
+            ..Default::default()
+        };
+        let alice = ClientFFI::new(b"alice".to_vec(), keypair, config);
+        let group = alice.create_group(None)?;
+
+        let Err(MlSrsError::CallbackError {
+            domain,
+            code,
+            message,
+        }) = group.write_to_storage()
+        else {
+            panic!("expected the error thrown by the callback");
+        };
+        assert_eq!(domain, "keychain");
+        assert_eq!(code, -25300);
+        assert_eq!(message, "item not found");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify() -> Result<(), MlSrsError> {
+        for cipher_suite in supported_cipher_suites(None) {
+            let keypair = generate_signature_keypair(cipher_suite)?;
+            let signature = sign(cipher_suite, keypair.secret_key, b"data".to_vec())?;
+            let public_key = keypair.public_key;
+
+            assert!(verify(
+                cipher_suite,
+                public_key.clone(),
+                signature.clone(),
+                b"data".to_vec()
+            )?);
+            assert!(!verify(
+                cipher_suite,
+                public_key.clone(),
+                signature,
+                b"other data".to_vec()
+            )?);
+            assert!(!verify(
+                cipher_suite,
+                public_key,
+                vec![0; 3],
+                b"data".to_vec()
+            )?);
+            assert!(matches!(
+                verify(
+                    cipher_suite,
+                    SignaturePublicKeyFFI { bytes: vec![1; 3] },
+                    vec![0; 64],
+                    b"data".to_vec()
+                ),
+                Err(MlSrsError::MalformedPublicKey)
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hpke_with_configured_provider() -> Result<(), MlSrsError> {
+        let crypto_provider = ClientConfigFFI::default().crypto_provider;
+        let cipher_suite = CipherSuiteFFI::Curve25519ChaCha;
+        let keypair = generate_hpke_keypair(cipher_suite, Some(crypto_provider.clone()))?;
+
+        let ciphertext = hpke_seal(
+            cipher_suite,
+            keypair.public_key.clone(),
+            b"info".to_vec(),
+            None,
+            b"plaintext".to_vec(),
+            Some(crypto_provider.clone()),
+        )?;
+        let plaintext = hpke_open(
+            keypair,
+            b"info".to_vec(),
+            None,
+            ciphertext,
+            Some(crypto_provider.clone()),
+        )?;
+        assert_eq!(plaintext, b"plaintext");
+        assert_eq!(random_bytes(16, Some(crypto_provider))?.len(), 16);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_signature_keypair_from_seed() -> Result<(), MlSrsError> {
+        let seed = vec![7; 32];
+        for cipher_suite in supported_cipher_suites(None) {
+            if matches!(cipher_suite, CipherSuiteFFI::P256Aes128) {
+                assert!(signature_keypair_from_seed(cipher_suite, seed.clone()).is_err());
+                continue;
+            }
+
+            let short = signature_keypair_from_seed(cipher_suite, seed[..31].to_vec());
+            assert!(matches!(
+                short,
+                Err(MlSrsError::SeedTooShort { size: 31, .. })
+            ));
+
+            let keypair = signature_keypair_from_seed(cipher_suite, seed.clone())?;
+            let again = signature_keypair_from_seed(cipher_suite, seed.clone())?;
+            assert_eq!(keypair.public_key.bytes, again.public_key.bytes);
+
+            let signature = sign(cipher_suite, keypair.secret_key, b"data".to_vec())?;
+            assert!(verify(
+                cipher_suite,
+                keypair.public_key,
+                signature,
+                b"data".to_vec()
+            )?);
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the key reference `b"enclave key"` to `keypair`.
+    #[derive(Debug)]
+    struct EnclaveKeyResolver {
+        keypair: SignatureKeypairFFI,
+        signatures: Mutex<u32>,
+    }
+
+    impl SigningKeyResolverProtocol for EnclaveKeyResolver {
+        fn public_key(
+            &self,
+            _cipher_suite: CipherSuiteFFI,
+            key_reference: Vec<u8>,
+        ) -> Result<SignaturePublicKeyFFI, MlSrsError> {
+            assert_eq!(key_reference, b"enclave key");
+            Ok(self.keypair.public_key.clone())
+        }
+
+        fn sign(
+            &self,
+            cipher_suite: CipherSuiteFFI,
+            key_reference: Vec<u8>,
+            data: Vec<u8>,
+        ) -> Result<Vec<u8>, MlSrsError> {
+            assert_eq!(key_reference, b"enclave key");
+            *self.signatures.lock().unwrap() += 1;
+            sign(cipher_suite, self.keypair.secret_key.clone(), data)
+        }
+    }
+
+    #[test]
+    fn test_signing_key_resolver() -> Result<(), MlSrsError> {
+        let cipher_suite = CipherSuiteFFI::Curve25519ChaCha;
+        let keypair = generate_signature_keypair(cipher_suite)?;
+        let key_reference = SignatureKeypairFFI {
+            cipher_suite,
+            public_key: keypair.public_key.clone(),
+            secret_key: SignatureSecretKeyFFI {
+                bytes: b"enclave key".to_vec().into(),
+            },
+        };
+        let resolver = Arc::new(EnclaveKeyResolver {
+            keypair,
+            signatures: Mutex::new(0),
+        });
         let alice_config = ClientConfigFFI {
-            group_state_storage: Arc::new(CustomGroupStateStorage::new()),
+            signing_key_resolver: Some(resolver.clone()),
             ..Default::default()
         };
-        let alice_keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
-        let alice = ClientFFI::new(b"alice".to_vec(), alice_keypair, alice_config);
+        let alice = ClientFFI::new(b"alice".to_vec(), key_reference, alice_config);
+        let bob_keypair = generate_signature_keypair(cipher_suite)?;
+        let bob = ClientFFI::new(b"bob".to_vec(), bob_keypair, ClientConfigFFI::default());
 
-        let bob_config = ClientConfigFFI {
-            group_state_storage: Arc::new(CustomGroupStateStorage::new()),
+        // Bob verifies the signatures Alice made through the resolver.
+        let alice_group = alice.create_group(None)?;
+        let bob_key_package = bob.generate_key_package_message(None, None)?;
+        let commit = alice_group.add_members(vec![Arc::new(bob_key_package)])?;
+        bob.join_group(&commit.welcome_message.unwrap())?;
+        assert!(*resolver.signatures.lock().unwrap() > 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_secret_expand() -> Result<(), MlSrsError> {
+        let (alice_group, bob_group) = setup_test()?;
+
+        let alice_secret = alice_group.export_secret(b"label".to_vec(), Vec::new(), 32)?;
+        let bob_secret = bob_group.export_secret(b"label".to_vec(), Vec::new(), 32)?;
+        assert!(alice_secret.constant_time_eq(bob_secret.clone()));
+
+        let alice_expanded = alice_secret.expand(b"info".to_vec(), 16)?;
+        let bob_expanded = bob_secret.expand(b"info".to_vec(), 16)?;
+        assert_eq!(alice_expanded.reveal().len(), 16);
+        assert!(alice_expanded.constant_time_eq(bob_expanded));
+        assert!(!alice_expanded.constant_time_eq(alice_secret));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replenish_key_packages() -> Result<(), MlSrsError> {
+        let config = ClientConfigFFI {
+            key_package_policy: Some(KeyPackagePolicyFFI {
+                threshold: 2,
+                target_count: 3,
+            }),
+            ..Default::default()
+        };
+        let expired = KeyPackageDataFFI {
+            key_package_bytes: vec![1],
+            init_key_data: vec![2],
+            leaf_node_key_data: vec![3],
+            expiration: 0,
+        };
+        config
+            .client_keypackage_storage
+            .insert(b"expired".to_vec(), expired)?;
+        let keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let alice = ClientFFI::new(b"alice".to_vec(), keypair, config);
+
+        // The expired key package doesn't count towards the threshold.
+        assert_eq!(alice.replenish_key_packages()?.len(), 3);
+        assert!(alice.replenish_key_packages()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_storage() -> Result<(), MlSrsError> {
+        let directory =
+            std::env::temp_dir().join(format!("mls-rs-uniffi-file-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        check_storage(Arc::new(FileStorage::open(directory.clone())?))?;
+        std::fs::remove_dir_all(directory)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_storage() -> Result<(), MlSrsError> {
+        check_storage(Arc::new(SqliteGroupStateStorage::open(":memory:")?))
+    }
+
+    #[test]
+    fn test_encrypted_storage() -> Result<(), MlSrsError> {
+        #[derive(Debug)]
+        struct FixedKey(Vec<u8>);
+
+        impl StorageKeyProtocol for FixedKey {
+            fn key(&self, _group_id: Vec<u8>) -> Result<Vec<u8>, MlSrsError> {
+                Ok(self.0.clone())
+            }
+        }
+
+        let inner = Arc::new(CustomGroupStateStorage::new());
+        let cipher_suite = CipherSuiteFFI::Curve25519ChaCha;
+        let storage = encrypted_group_state_storage(
+            inner.clone(),
+            cipher_suite,
+            Arc::new(FixedKey(vec![1; 32])),
+            None,
+        )?;
+        let epoch = EpochRecordFFI {
+            id: 1,
+            data: b"epoch".to_vec(),
+        };
+        storage.write(
+            b"group".to_vec(),
+            b"state".to_vec(),
+            vec![epoch],
+            vec![],
+            None,
+        )?;
+
+        let sealed = inner.state(b"group".to_vec())?.unwrap();
+        assert_ne!(sealed, b"state".to_vec());
+        assert_eq!(storage.state(b"group".to_vec())?, Some(b"state".to_vec()));
+        assert_eq!(
+            storage.epoch(b"group".to_vec(), 1)?,
+            Some(b"epoch".to_vec())
+        );
+
+        // Records don't open with another key or under another group id.
+        let other_key = encrypted_group_state_storage(
+            inner.clone(),
+            cipher_suite,
+            Arc::new(FixedKey(vec![2; 32])),
+            None,
+        )?;
+        assert!(other_key.state(b"group".to_vec()).is_err());
+        inner.write(b"other".to_vec(), sealed, vec![], vec![], None)?;
+        assert!(storage.state(b"other".to_vec()).is_err());
+
+        // Groups written through the wrapper are loaded through it.
+        let keypair = generate_signature_keypair(cipher_suite)?;
+        let config = || ClientConfigFFI {
+            group_state_storage: storage.clone(),
+            ..Default::default()
+        };
+        let alice = ClientFFI::new(b"alice".to_vec(), keypair.clone(), config());
+        let group = alice.create_group(None)?;
+        group.write_to_storage()?;
+
+        let alice = ClientFFI::new(b"alice".to_vec(), keypair, config());
+        assert_eq!(alice.load_group(group.group_id()?)?.current_epoch()?, 0);
+
+        Ok(())
+    }
+
+    /// Exercise a storage directly and through a client.
+    fn check_storage<S>(storage: Arc<S>) -> Result<(), MlSrsError>
+    where
+        S: GroupStateStorageProtocol + KeyPackageStorageProtocol + 'static,
+    {
+        let epoch = |id| EpochRecordFFI {
+            id,
+            data: vec![id as u8],
+        };
+        storage.write(
+            b"group".to_vec(),
+            b"state".to_vec(),
+            vec![epoch(1), epoch(2)],
+            vec![],
+            None,
+        )?;
+        assert_eq!(storage.state(b"group".to_vec())?, Some(b"state".to_vec()));
+        assert_eq!(storage.epoch(b"group".to_vec(), 1)?, Some(vec![1]));
+        assert_eq!(storage.max_epoch_id(b"group".to_vec())?, Some(2));
+        assert_eq!(storage.group_ids()?, vec![b"group".to_vec()]);
+
+        storage.write(
+            b"group".to_vec(),
+            b"state".to_vec(),
+            vec![epoch(3)],
+            vec![],
+            Some(2),
+        )?;
+        assert_eq!(storage.epoch(b"group".to_vec(), 1)?, None);
+        assert_eq!(storage.epoch(b"group".to_vec(), 2)?, Some(vec![2]));
+
+        storage.delete_group(b"group".to_vec())?;
+        assert_eq!(storage.state(b"group".to_vec())?, None);
+
+        let key_package = KeyPackageDataFFI {
+            key_package_bytes: vec![1],
+            init_key_data: vec![2],
+            leaf_node_key_data: vec![3],
+            expiration: 4,
+        };
+        storage.insert(b"id".to_vec(), key_package)?;
+        assert_eq!(storage.key_package_ids()?, vec![b"id".to_vec()]);
+        let key_package = storage.get(b"id".to_vec())?.unwrap();
+        assert_eq!(key_package.init_key_data, vec![2]);
+        assert_eq!(key_package.expiration, 4);
+        storage.delete(b"id".to_vec())?;
+        assert!(storage.get(b"id".to_vec())?.is_none());
+
+        // A group written by one client is loaded by the next one.
+        let keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let config = || ClientConfigFFI {
+            group_state_storage: storage.clone(),
             ..Default::default()
         };
+        let alice = ClientFFI::new(b"alice".to_vec(), keypair.clone(), config());
+        let group = alice.create_group(None)?;
+        let commit = group.commit(Vec::new())?;
+        group.process_incoming_message(commit.commit_message)?;
+        group.write_to_storage()?;
+
+        let alice = ClientFFI::new(b"alice".to_vec(), keypair, config());
+        let loaded = alice.load_group(group.group_id()?)?;
+        assert_eq!(loaded.current_epoch()?, 1);
+
+        Ok(())
+    }
+
+    /// Send handshake messages as PublicMessage, so they can be
+    /// parsed without processing them.
+    fn public_handshake_config() -> ClientConfigFFI {
+        ClientConfigFFI {
+            group_state_storage: Arc::new(CustomGroupStateStorage::new()),
+            encrypt_control_messages: false,
+            ..Default::default()
+        }
+    }
+
+    fn setup_test() -> Result<(GroupFFI, GroupFFI), MlSrsError> {
+        setup_test_with_config(|| ClientConfigFFI {
+            group_state_storage: Arc::new(CustomGroupStateStorage::new()),
+            ..Default::default()
+        })
+    }
+
+    fn setup_test_with_config(
+        config: impl Fn() -> ClientConfigFFI,
+    ) -> Result<(GroupFFI, GroupFFI), MlSrsError> {
+        let alice_config = config();
+        let alice_keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
+        let alice = ClientFFI::new(b"alice".to_vec(), alice_keypair, alice_config);
+
+        let bob_config = config();
         let bob_keypair = generate_signature_keypair(CipherSuiteFFI::Curve25519ChaCha)?;
         let bob = ClientFFI::new(b"bob".to_vec(), bob_keypair, bob_config);
 
         let alice_group = alice.create_group(None)?;
-        let bob_key_package = bob.generate_key_package_message()?;
+        let bob_key_package = bob.generate_key_package_message(None, None)?;
         let commit = alice_group.add_members(vec![Arc::new(bob_key_package)])?;
         alice_group.process_incoming_message(commit.commit_message)?;
 
@@ -289,6 +1038,7 @@ mod tests {
             group_state: Vec<u8>,
             epoch_inserts: Vec<EpochRecordFFI>,
             epoch_updates: Vec<EpochRecordFFI>,
+            prune_before: Option<u64>,
         ) -> Result<(), MlSrsError> {
             let mut groups = self.lock();
 
@@ -307,6 +1057,12 @@ mod tests {
                 }
             }
 
+            if let Some(before_epoch_id) = prune_before {
+                group
+                    .epoch_data
+                    .retain(|record| record.id >= before_epoch_id);
+            }
+
             Ok(())
         }
 
@@ -317,5 +1073,14 @@ mod tests {
                 .and_then(|MockGroupStateData { epoch_data, .. }| epoch_data.last())
                 .map(|last| last.id))
         }
+
+        fn delete_group(&self, group_id: Vec<u8>) -> Result<(), MlSrsError> {
+            self.lock().remove(&group_id);
+            Ok(())
+        }
+
+        fn group_ids(&self) -> Result<Vec<Vec<u8>>, MlSrsError> {
+            Ok(self.lock().keys().cloned().collect())
+        }
     }
 }
@@ -1,6 +1,6 @@
-use crate::config::group_context::CipherSuiteFFI;
+use crate::config::group_context::{CipherSuiteFFI, GroupContextFFI};
 use crate::ExtensionListFFI;
-use mls_rs::group::{CommitEffect, ProposalMessageDescription, ProposalSender, Sender};
+use mls_rs::group::{CommitEffect, ProposalMessageDescription, ProposalSender, Roster, Sender};
 use mls_rs::mls_rules::ProposalInfo;
 use mls_rs::MlsMessage;
 use std::sync::Arc;
@@ -9,6 +9,10 @@ use crate::config::SigningIdentityFFI;
 use crate::MlSrsError;
 use mls_rs::error::{IntoAnyError, MlsError};
 use mls_rs::group::proposal::Proposal;
+use mls_rs::mls_rs_codec::{MlsDecode, MlsEncode, MlsSize};
+use mls_rs_core::extension::ExtensionType;
+use mls_rs_core::group::Capabilities;
+use mls_rs_core::identity::SigningIdentity;
 
 ///Matches types in mls_rs::group::message_processor
 
@@ -47,6 +51,37 @@ impl MessageFFI {
         self.inner.is_welcome()
     }
 
+    /// Cipher suite of a Welcome, GroupInfo or KeyPackage message.
+    pub fn cipher_suite(&self) -> Result<Option<CipherSuiteFFI>, MlSrsError> {
+        self.inner.cipher_suite().map(TryInto::try_into).transpose()
+    }
+
+    /// References of the key packages a Welcome message is encrypted
+    /// to, empty for other messages.
+    ///
+    /// Used to pick the matching local key package, or to route the
+    /// welcome to its recipients.
+    pub fn welcome_key_package_references(&self) -> Vec<Vec<u8>> {
+        self.inner
+            .welcome_key_package_references()
+            .into_iter()
+            .map(|reference| reference.to_vec())
+            .collect()
+    }
+
+    /// The contents of a GroupInfo message, `None` for other wire
+    /// formats.
+    ///
+    /// The GroupInfo is not validated, see
+    /// [`ExternalClientFFI::validate_group_info`](crate::external_client::ExternalClientFFI::validate_group_info).
+    pub fn group_info(&self) -> Result<Option<GroupInfoFFI>, MlSrsError> {
+        self.inner
+            .clone()
+            .into_group_info()
+            .map(TryInto::try_into)
+            .transpose()
+    }
+
     pub fn private_message_content_type(&self) -> Option<u8> {
         let ciphertext_maybe = self.inner.private_message();
         let Some(ciphertext) = ciphertext_maybe else {
@@ -66,6 +101,32 @@ impl MessageFFI {
         }
     }
 
+    /// The key package of a KeyPackage message, e.g., fetched from
+    /// a key package directory.
+    ///
+    /// Returns `None` for other wire formats and an error for key
+    /// packages which can't be converted.
+    pub fn key_package(&self) -> Result<Option<Arc<KeyPackageFFI>>, MlSrsError> {
+        let key_package: Option<KeyPackageFFI> = self
+            .inner
+            .clone()
+            .into_key_package()
+            .map(TryInto::try_into)
+            .transpose()?;
+        Ok(key_package.map(Arc::new))
+    }
+
+    /// The raw authenticated data of a PrivateMessage, `None` for
+    /// other wire formats.
+    ///
+    /// The message is not decrypted, so the data is not yet
+    /// authenticated. Its interpretation is left to the application.
+    pub fn unchecked_authenticated_data(&self) -> Option<Vec<u8>> {
+        self.inner
+            .private_message()
+            .map(|ciphertext| ciphertext.authenticated_data.as_slice().to_vec())
+    }
+
     //For a TwoMLS first message that contains the Combined Welcome
     //in its authenticated data
     pub fn unchecked_auth_data(
@@ -75,15 +136,13 @@ impl MessageFFI {
         let ciphertext_maybe = self.inner.private_message();
 
         let Some(ciphertext) = ciphertext_maybe else {
-            return Err(MlSrsError::MlsError {
-                inner: MlsError::UnexpectedMessageType,
-            });
+            return Err(MlsError::UnexpectedMessageType.into());
         };
         if ciphertext.content_type as u8 != expected_outer_type {
-            return Err(MlSrsError::UnexpectedMessageTypeDetailed(
-                expected_outer_type,
-                ciphertext.content_type as u8,
-            ));
+            return Err(MlSrsError::UnexpectedMessageTypeDetailed {
+                expected: expected_outer_type,
+                actual: ciphertext.content_type as u8,
+            });
         }
 
         if ciphertext.authenticated_data.is_empty() {
@@ -117,15 +176,13 @@ impl MessageFFI {
         let ciphertext_maybe = self.inner.private_message();
 
         let Some(ciphertext) = ciphertext_maybe else {
-            return Err(MlSrsError::MlsError {
-                inner: MlsError::UnexpectedMessageType,
-            });
+            return Err(MlsError::UnexpectedMessageType.into());
         };
         if ciphertext.content_type as u8 != expected_outer_type {
-            return Err(MlSrsError::UnexpectedMessageTypeDetailed(
-                expected_outer_type,
-                ciphertext.content_type as u8,
-            ));
+            return Err(MlSrsError::UnexpectedMessageTypeDetailed {
+                expected: expected_outer_type,
+                actual: ciphertext.content_type as u8,
+            });
         }
 
         if ciphertext.authenticated_data.is_empty() {
@@ -138,10 +195,10 @@ impl MessageFFI {
             .private_message()
             .map(|c| c.content_type as u8);
         if inner_content_type != expected_inner_type {
-            return Err(MlSrsError::UnexpectedMessageTypeDetailed(
-                expected_inner_type.unwrap_or(0),
-                inner_content_type.unwrap_or(0),
-            ));
+            return Err(MlSrsError::UnexpectedMessageTypeDetailed {
+                expected: expected_inner_type.unwrap_or(0),
+                actual: inner_content_type.unwrap_or(0),
+            });
         }
 
         Ok(Some(Arc::new(MessageFFI {
@@ -156,6 +213,31 @@ impl From<mls_rs::MlsMessage> for MessageFFI {
     }
 }
 
+/// A [`mls_rs::group::GroupInfo`] wrapper.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct GroupInfoFFI {
+    /// Context of the group, including its epoch, id and tree hash.
+    pub group_context: GroupContextFFI,
+    /// Whether the GroupInfo contains an external public key, which
+    /// is required to join with an external commit.
+    pub has_external_pub: bool,
+    /// The GroupInfo extensions.
+    pub extensions: Arc<ExtensionListFFI>,
+}
+
+impl TryFrom<mls_rs::group::GroupInfo> for GroupInfoFFI {
+    type Error = MlSrsError;
+
+    fn try_from(group_info: mls_rs::group::GroupInfo) -> Result<Self, Self::Error> {
+        let extensions = group_info.extensions().clone();
+        Ok(Self {
+            group_context: group_info.group_context().clone().try_into()?,
+            has_external_pub: extensions.has_extension(ExtensionType::EXTERNAL_PUB),
+            extensions: Arc::new(extensions.into()),
+        })
+    }
+}
+
 /// A [`mls_rs::group::ReceivedMessage`] wrapper.
 #[derive(Clone, Debug, uniffi::Enum)]
 pub enum ReceivedMessageFFI {
@@ -165,6 +247,10 @@ pub enum ReceivedMessageFFI {
     /// application-specific and is not determined by MLS.
     ApplicationMessage {
         sender: Arc<SigningIdentityFFI>,
+        /// Leaf index of the sender.
+        sender_index: u32,
+        /// The epoch the message was encrypted in.
+        epoch: u64,
         data: Vec<u8>,
         authenticated_data: Vec<u8>,
     },
@@ -180,29 +266,111 @@ pub enum ReceivedMessageFFI {
     // https://github.com/awslabs/mls-rs/issues/98 is fixed.
     /// A proposal was received.
     ReceivedProposal {
-        sender: Arc<SigningIdentityFFI>,
+        sender: ProposalSenderFFI,
         proposal: ProposalFFI,
+        /// Reference to the cached proposal, see
+        /// [`GroupFFI::commit_proposals`](crate::group::GroupFFI::commit_proposals).
+        proposal_ref: Vec<u8>,
         authenticated_data: Vec<u8>,
     },
 
     /// Validated GroupInfo object.
-    GroupInfo,
+    GroupInfo { group_info: GroupInfoFFI },
     /// Validated welcome message.
-    Welcome,
+    ///
+    /// Welcome messages are processed with
+    /// [`ClientFFI::join_group`](crate::client::ClientFFI::join_group),
+    /// the key package references identify the recipients.
+    Welcome {
+        cipher_suite: CipherSuiteFFI,
+        key_package_references: Vec<Vec<u8>>,
+    },
     /// Validated key package.
-    KeyPackage,
+    KeyPackage { key_package: Arc<KeyPackageFFI> },
+}
+
+/// A [`mls_rs::group::Sender`] wrapper.
+#[derive(Clone, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum SenderFFI {
+    /// A current member of the group.
+    Member { leaf_index: u32 },
+    /// An external sender, identified by its index in the group's
+    /// external senders extension.
+    External { index: u32 },
+    /// A new member proposing to add itself.
+    NewMemberProposal,
+    /// A new member joining with an external commit.
+    NewMemberCommit,
+}
+
+impl From<&Sender> for SenderFFI {
+    fn from(sender: &Sender) -> Self {
+        match *sender {
+            Sender::Member(leaf_index) => Self::Member { leaf_index },
+            Sender::External(index) => Self::External { index },
+            Sender::NewMemberProposal => Self::NewMemberProposal,
+            Sender::NewMemberCommit => Self::NewMemberCommit,
+        }
+    }
+}
+
+/// A [`mls_rs::group::ProposalSender`] wrapper.
+#[derive(Clone, Debug, uniffi::Enum)]
+pub enum ProposalSenderFFI {
+    /// A current member of the group.
+    Member(Arc<SigningIdentityFFI>),
+    /// An external sender configured in the group's external senders
+    /// extension, identified by its index in that extension.
+    External { index: u32 },
+    /// A new member proposing to add itself, e.g., with an external
+    /// join proposal.
+    NewMember { key_package: Arc<KeyPackageFFI> },
 }
 
 #[derive(Clone, Debug, uniffi::Enum)]
 pub enum CommitEffectFFI {
     NewEpoch {
         applied_proposals: Vec<ProposalFFI>,
-        unused_proposals: Vec<ProposalFFI>,
+        unused_proposals: Vec<ProposalInfoFFI>,
     },
     ReInit,
     Removed,
 }
 
+/// A proposal together with its sender.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct ProposalInfoFFI {
+    pub proposal: ProposalFFI,
+    pub sender: SenderFFI,
+    /// The signing identity of a member sender. `None` for other
+    /// senders and for members who are no longer in the group.
+    pub signing_identity: Option<Arc<SigningIdentityFFI>>,
+}
+
+impl ProposalInfoFFI {
+    /// Look up the sender of `proposal_info` in `roster`, unless its
+    /// leaf is one of `removed`.
+    pub(crate) fn new(
+        proposal_info: ProposalInfo<Proposal>,
+        roster: &Roster,
+        removed: &[u32],
+    ) -> Result<Self, MlSrsError> {
+        let sender = SenderFFI::from(&proposal_info.sender);
+        let signing_identity = match proposal_info.sender {
+            Sender::Member(index) if !removed.contains(&index) => roster
+                .member_with_index(index)
+                .ok()
+                .map(|member| Arc::new(member.signing_identity.into())),
+            _ => None,
+        };
+        Ok(Self {
+            proposal: proposal_info.try_into()?,
+            sender,
+            signing_identity,
+        })
+    }
+}
+
 #[derive(Clone, Debug, uniffi::Enum)]
 pub enum ProposalFFI {
     // Add(alloc::boxed::Box<AddProposal>),
@@ -213,11 +381,14 @@ pub enum ProposalFFI {
     },
     // Replace(Arc<ReplaceProposalFFI>),
     Psk,
-    Remove(u32), // Psk(PreSharedKeyProposal),
-                 // ReInit(ReInitProposal),
-                 // ExternalInit(ExternalInit),
-                 // GroupContextExtensions(ExtensionList),
-                 // Custom(CustomProposal),
+    Remove(u32),
+    /// New group context extensions.
+    GroupContextExtensions(Arc<ExtensionListFFI>),
+    /// Any other proposal, e.g., ReInit, ExternalInit or a custom
+    /// proposal, identified by its proposal type.
+    Other {
+        proposal_type: u16,
+    },
 }
 
 // #[uniffi::export]
@@ -253,58 +424,63 @@ impl ProposalFFI {
                 sender_index: _,
             } => Some(new.clone()),
             // ProposalFFI::Replace(r) => Some(Arc::new(r.leaf_node.signing_identity.clone())),
-            ProposalFFI::Psk => None,
-            ProposalFFI::Remove(_) => None,
+            ProposalFFI::Psk
+            | ProposalFFI::Remove(_)
+            | ProposalFFI::GroupContextExtensions(_)
+            | ProposalFFI::Other { .. } => None,
         }
     }
 }
 
-impl TryFrom<ProposalInfo<Proposal>> for ProposalFFI {
-    type Error = MlSrsError;
-
-    fn try_from(value: ProposalInfo<Proposal>) -> Result<Self, Self::Error> {
-        match value.proposal {
+impl ProposalFFI {
+    /// Convert a proposal, `sender_index` is the index of the member
+    /// who sent it, which is required for update proposals.
+    fn new(proposal: Proposal, sender_index: Option<u32>) -> Result<Self, MlSrsError> {
+        match proposal {
             Proposal::Add(k) => {
                 let key_package = k.key_package().clone();
                 Ok(ProposalFFI::Add(Arc::new(key_package.try_into()?)))
             }
             Proposal::Update(u) => {
-                let signing_identity = u.signing_identity().clone();
-                match value.sender {
-                    Sender::Member(index) => Ok(ProposalFFI::Update {
-                        new: Arc::new(signing_identity.into()),
-                        sender_index: index,
-                    }),
-                    _ => Err(MlSrsError::UnexpectedProposalSender),
-                }
+                let sender_index = sender_index.ok_or(MlSrsError::UnexpectedProposalSender)?;
+                Ok(ProposalFFI::Update {
+                    new: Arc::new(u.signing_identity().clone().into()),
+                    sender_index,
+                })
             }
             Proposal::Psk(_) => Ok(ProposalFFI::Psk),
-            _ => Ok(ProposalFFI::Remove(0)),
+            Proposal::Remove(remove) => Ok(ProposalFFI::Remove(remove.to_remove())),
+            Proposal::GroupContextExtensions(extensions) => Ok(
+                ProposalFFI::GroupContextExtensions(Arc::new(extensions.into())),
+            ),
+            proposal => Ok(ProposalFFI::Other {
+                proposal_type: proposal.proposal_type().raw_value(),
+            }),
         }
     }
 }
 
+impl TryFrom<ProposalInfo<Proposal>> for ProposalFFI {
+    type Error = MlSrsError;
+
+    fn try_from(value: ProposalInfo<Proposal>) -> Result<Self, Self::Error> {
+        let sender_index = match value.sender {
+            Sender::Member(index) => Some(index),
+            _ => None,
+        };
+        ProposalFFI::new(value.proposal, sender_index)
+    }
+}
+
 impl TryFrom<ProposalMessageDescription> for ProposalFFI {
     type Error = MlSrsError;
 
     fn try_from(value: ProposalMessageDescription) -> Result<Self, Self::Error> {
-        match value.proposal {
-            Proposal::Add(k) => {
-                let key_package = k.key_package().clone();
-                Ok(ProposalFFI::Add(Arc::new(key_package.try_into()?)))
-            }
-            Proposal::Update(u) => {
-                let signing_identity = u.signing_identity().clone();
-                match value.sender {
-                    ProposalSender::Member(index) => Ok(ProposalFFI::Update {
-                        new: Arc::new(signing_identity.into()),
-                        sender_index: index,
-                    }),
-                    _ => Err(MlSrsError::UnexpectedProposalSender),
-                }
-            }
-            _ => Ok(ProposalFFI::Remove(0)),
-        }
+        let sender_index = match value.sender {
+            ProposalSender::Member(index) => Some(index),
+            _ => None,
+        };
+        ProposalFFI::new(value.proposal, sender_index)
     }
 }
 
@@ -331,18 +507,109 @@ pub struct LeafIndexFFI {
 //     pub signature: Vec<u8>,
 // }
 
-// #[derive(Clone, Debug, uniffi::Enum)]
-// pub enum LeafNodeSource {
-//     KeyPackage(Lifetime),
-//     Update,
-//     Commit(Vec<u8>),
-// }
+/// The capabilities advertised by a leaf node, as raw values.
+///
+/// Before changing the group context extensions, check that all
+/// members list the new extension types here.
+#[derive(Clone, Debug, PartialEq, Eq, uniffi::Record)]
+pub struct CapabilitiesFFI {
+    pub protocol_versions: Vec<u16>,
+    pub cipher_suites: Vec<u16>,
+    pub extensions: Vec<u16>,
+    pub proposals: Vec<u16>,
+    pub credentials: Vec<u16>,
+}
 
-// #[derive(Clone, Debug, uniffi::Record)]
-// pub struct Lifetime {
-//     pub not_before: u64,
-//     pub not_after: u64,
-// }
+impl From<&Capabilities> for CapabilitiesFFI {
+    fn from(capabilities: &Capabilities) -> Self {
+        Self {
+            protocol_versions: capabilities
+                .protocol_versions
+                .iter()
+                .map(|version| version.raw_value())
+                .collect(),
+            cipher_suites: capabilities
+                .cipher_suites
+                .iter()
+                .map(|cipher_suite| cipher_suite.raw_value())
+                .collect(),
+            extensions: capabilities
+                .extensions
+                .iter()
+                .map(|extension_type| extension_type.raw_value())
+                .collect(),
+            proposals: capabilities
+                .proposals
+                .iter()
+                .map(|proposal_type| proposal_type.raw_value())
+                .collect(),
+            credentials: capabilities
+                .credentials
+                .iter()
+                .map(|credential_type| credential_type.raw_value())
+                .collect(),
+        }
+    }
+}
+
+/// Validity period of a key package leaf node, in seconds since the
+/// Unix epoch.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct LifetimeFFI {
+    pub not_before: u64,
+    pub not_after: u64,
+}
+
+/// A leaf node as encoded in RFC 9420, section 7.2.
+#[derive(Clone, Debug, MlsSize, MlsEncode, MlsDecode)]
+pub(crate) struct LeafNodeData {
+    #[mls_codec(with = "mls_rs_codec::byte_vec")]
+    pub public_key: Vec<u8>,
+    pub signing_identity: SigningIdentity,
+    capabilities: Capabilities,
+    leaf_node_source: LeafNodeSourceData,
+    extensions: mls_rs::ExtensionList,
+    #[mls_codec(with = "mls_rs_codec::byte_vec")]
+    pub signature: Vec<u8>,
+}
+
+#[derive(Clone, Debug, MlsSize, MlsEncode, MlsDecode)]
+#[repr(u8)]
+enum LeafNodeSourceData {
+    KeyPackage(LifetimeData) = 1u8,
+    Update = 2u8,
+    Commit(Vec<u8>) = 3u8,
+}
+
+#[derive(Clone, Debug, MlsSize, MlsEncode, MlsDecode)]
+struct LifetimeData {
+    not_before: u64,
+    not_after: u64,
+}
+
+impl LeafNodeData {
+    /// Decode the leaf node of a key package, which mls-rs keeps
+    /// crate private.
+    pub(crate) fn from_key_package(key_package: &mls_rs::KeyPackage) -> Result<Self, MlSrsError> {
+        let encoded = key_package.mls_encode_to_vec()?;
+        let mut reader = encoded.as_slice();
+        let _version = u16::mls_decode(&mut reader)?;
+        let _cipher_suite = u16::mls_decode(&mut reader)?;
+        let _init_key = Vec::<u8>::mls_decode(&mut reader)?;
+        Ok(Self::mls_decode(&mut reader)?)
+    }
+
+    /// Lifetime of a leaf node created for a key package.
+    pub(crate) fn lifetime(&self) -> Option<LifetimeFFI> {
+        match &self.leaf_node_source {
+            LeafNodeSourceData::KeyPackage(lifetime) => Some(LifetimeFFI {
+                not_before: lifetime.not_before,
+                not_after: lifetime.not_after,
+            }),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Clone, Debug, uniffi::Object)]
 pub struct KeyPackageFFI {
@@ -353,6 +620,8 @@ pub struct KeyPackageFFI {
     // pub leaf_node: LeafNodeFFI,
     pub extensions: ExtensionListFFI,
     pub signature: Vec<u8>,
+    /// Lifetime of the key package leaf node.
+    pub lifetime: Option<LifetimeFFI>,
 }
 
 #[uniffi::export]
@@ -372,6 +641,10 @@ impl KeyPackageFFI {
     pub fn get_leaf_node_signing_identity(&self) -> SigningIdentityFFI {
         self.leaf_node_signing_identity.clone()
     }
+
+    pub fn get_lifetime(&self) -> Option<LifetimeFFI> {
+        self.lifetime.clone()
+    }
 }
 
 impl TryFrom<mls_rs::KeyPackage> for KeyPackageFFI {
@@ -379,6 +652,7 @@ impl TryFrom<mls_rs::KeyPackage> for KeyPackageFFI {
 
     fn try_from(value: mls_rs::KeyPackage) -> Result<Self, Self::Error> {
         let signing_identity = value.signing_identity().clone();
+        let leaf_node = LeafNodeData::from_key_package(&value)?;
 
         Ok(KeyPackageFFI {
             version: ProtocolVersionFFI {
@@ -390,6 +664,7 @@ impl TryFrom<mls_rs::KeyPackage> for KeyPackageFFI {
             leaf_node_signing_identity: signing_identity.into(),
             // leaf_node: value.leaf_node.into(),
             signature: value.signature,
+            lifetime: leaf_node.lifetime(),
         })
     }
 }
@@ -399,23 +674,37 @@ pub struct ProtocolVersionFFI {
     pub version: u16,
 }
 
-impl From<mls_rs::group::CommitEffect> for CommitEffectFFI {
-    fn from(value: mls_rs::group::CommitEffect) -> Self {
+impl CommitEffectFFI {
+    /// Convert the effect of a commit, `roster` is the roster after
+    /// the commit was applied.
+    pub(crate) fn new(value: mls_rs::group::CommitEffect, roster: &Roster) -> Self {
         match value {
-            CommitEffect::NewEpoch(new_epoch) => CommitEffectFFI::NewEpoch {
-                applied_proposals: new_epoch
+            CommitEffect::NewEpoch(new_epoch) => {
+                // Leaves of removed members may be reused by members
+                // added in the same commit.
+                let removed: Vec<u32> = new_epoch
                     .applied_proposals
-                    .into_iter()
-                    //warning - silently fails - TODO: try_collect
-                    .flat_map(|p| p.try_into())
-                    .collect(),
-                unused_proposals: new_epoch
-                    .unused_proposals
-                    .into_iter()
-                    //warning - silently fails - TODO: try_collect
-                    .flat_map(|p| p.try_into())
-                    .collect(),
-            },
+                    .iter()
+                    .filter_map(|p| match &p.proposal {
+                        Proposal::Remove(remove) => Some(remove.to_remove()),
+                        _ => None,
+                    })
+                    .collect();
+                CommitEffectFFI::NewEpoch {
+                    applied_proposals: new_epoch
+                        .applied_proposals
+                        .into_iter()
+                        //warning - silently fails - TODO: try_collect
+                        .flat_map(|p| p.try_into())
+                        .collect(),
+                    unused_proposals: new_epoch
+                        .unused_proposals
+                        .into_iter()
+                        //warning - silently fails - TODO: try_collect
+                        .flat_map(|p| ProposalInfoFFI::new(p, roster, &removed))
+                        .collect(),
+                }
+            }
             CommitEffect::Removed {
                 new_epoch: _,
                 remover: _,
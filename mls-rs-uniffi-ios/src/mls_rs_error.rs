@@ -1,41 +1,304 @@
 use mls_rs_core::error::IntoAnyError;
 
-#[derive(Debug, thiserror::Error, uniffi::Error)]
-#[uniffi(flat_error)]
+/// Errors returned by this library.
+///
+/// Errors from mls-rs and other libraries are converted to their
+/// description, the remaining variants carry their details as fields
+/// so they can be matched on by callers.
+///
+/// Errors thrown by callbacks are passed through mls-rs unchanged, so
+/// foreign code gets back the error it threw.
+#[derive(Clone, Debug, thiserror::Error, uniffi::Error)]
 #[non_exhaustive]
 pub enum MlSrsError {
-    #[error("A mls-rs error occurred: {inner}")]
+    #[error("A mls-rs error occurred: {message}")]
     MlsError {
-        #[from]
-        inner: mls_rs::error::MlsError,
-    },
-    #[error("An unknown error occurred: {inner}")]
-    AnyError {
-        #[from]
-        inner: mls_rs::error::AnyError,
-    },
-    #[error("A data encoding error occurred: {inner}")]
-    MlsCodecError {
-        #[from]
-        inner: mls_rs_core::mls_rs_codec::Error,
-    },
-    #[error("Unexpected callback error in UniFFI: {inner}")]
-    UnexpectedCallbackError {
-        #[from]
-        inner: uniffi::UnexpectedUniFFICallbackError,
+        message: String,
+        category: ErrorCategoryFFI,
     },
+    #[error("An unknown error occurred: {message}")]
+    AnyError { message: String },
+    #[error("A data encoding error occurred: {message}")]
+    MlsCodecError { message: String },
+    #[error("Unexpected callback error in UniFFI: {message}")]
+    UnexpectedCallbackError { message: String },
+    #[error("An I/O error occurred: {message}")]
+    IoError { message: String },
+    #[cfg(feature = "sqlite")]
+    #[error("A SQLite error occurred: {message}")]
+    SqliteError { message: String },
     #[error("Unexpected message format")]
     UnexpecteMessageFormat,
     #[error("Inconsistent Optional Parameters")]
     InconsistentOptionalParameters,
     #[error("Missing Basic Credential")]
     MissingBasicCredential,
-    #[error("Unexpected Message Format")]
-    UnexpectedMessageTypeDetailed(u8, u8),
+    #[error("Unexpected content type {actual}, expected {expected}")]
+    UnexpectedMessageTypeDetailed { expected: u8, actual: u8 },
     #[error("Unexpected Proposal")]
     UnexpectedProposalSender,
     #[error("Not Implemented")]
     NotImplemented,
+    #[error("Group size limit of {max_group_size} members exceeded")]
+    GroupSizeLimitExceeded { max_group_size: u32 },
+    #[error("Authenticated data of {size} bytes exceeds the maximum of {max_size} bytes")]
+    AuthenticatedDataTooLarge { size: u64, max_size: u32 },
+    /// A member doesn't support a capability required by the
+    /// RequiredCapabilities extension of the group, see
+    /// [`required_capabilities_extension`](crate::config::group_context::required_capabilities_extension).
+    #[error("Required {kind:?} capability {raw_value} is not supported")]
+    MissingRequiredCapability {
+        kind: CapabilityKindFFI,
+        raw_value: u16,
+    },
+    /// A custom proposal violates the configured
+    /// [`CustomProposalPolicyFFI`](crate::config::mls_rules::CustomProposalPolicyFFI).
+    #[error("Custom proposal of type {proposal_type} not allowed")]
+    CustomProposalNotAllowed { proposal_type: u16 },
+    #[error("Invalid key package: {reason}")]
+    InvalidKeyPackage { reason: String },
+    /// The message was sent in another epoch than the group is in.
+    ///
+    /// The epochs are known when the error was raised while
+    /// processing a message of a group.
+    #[error("Message epoch {message_epoch:?} does not match group epoch {group_epoch:?}")]
+    WrongEpoch {
+        message_epoch: Option<u64>,
+        group_epoch: Option<u64>,
+    },
+    #[error("Invalid signature")]
+    InvalidSignature,
+    /// A signature public key has the wrong size for its cipher
+    /// suite.
+    #[error("Malformed signature public key")]
+    MalformedPublicKey,
+    /// The client was wiped, see
+    /// [`ClientFFI::wipe`](crate::client::ClientFFI::wipe).
+    #[error("Client was wiped")]
+    Wiped,
+    /// A key package or leaf node is used outside of its lifetime.
+    #[error("Key package expired")]
+    KeyPackageExpired,
+    /// Several proposals in a commit modify the same leaf.
+    #[error("More than one proposal for leaf {leaf_index}")]
+    DuplicateProposal { leaf_index: u32 },
+    /// A seed is too short to derive a key from, see
+    /// [`signature_keypair_from_seed`](crate::client::signature_keypair_from_seed).
+    #[error("Seed of {size} bytes is shorter than {min_size} bytes")]
+    SeedTooShort { size: u64, min_size: u32 },
+    /// An error thrown by a callback, e.g., a Swift `NSError`.
+    ///
+    /// Callbacks should throw this variant to recognize their own
+    /// errors when they come back from a library call.
+    #[error("Callback error {domain} {code}: {message}")]
+    CallbackError {
+        domain: String,
+        code: i64,
+        message: String,
+    },
+}
+
+impl IntoAnyError for MlSrsError {
+    fn into_dyn_error(self) -> Result<Box<dyn std::error::Error + Send + Sync>, Self> {
+        Ok(Box::new(WrappedError(self)))
+    }
+}
+
+/// Carries a [`MlSrsError`] through mls-rs.
+///
+/// mls-rs wraps callback errors in an [`AnyError`](mls_rs::error::AnyError),
+/// which only exposes the source of the wrapped error.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct WrappedError(#[source] MlSrsError);
+
+/// Find a [`MlSrsError`] thrown by a callback in the sources of `error`.
+fn callback_error(error: &(dyn std::error::Error + 'static)) -> Option<MlSrsError> {
+    let mut source = error.source();
+    while let Some(error) = source {
+        if let Some(error) = error.downcast_ref::<MlSrsError>() {
+            return Some(error.clone());
+        }
+        source = error.source();
+    }
+    None
+}
+
+/// Broad classification of a [`MlSrsError`], see
+/// [`MlSrsError::category`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum ErrorCategoryFFI {
+    /// A message or group state violates the MLS protocol.
+    ProtocolViolation,
+    /// A message belongs to an epoch the group is no longer or not
+    /// yet in.
+    StaleEpoch,
+    /// Reading or writing a storage failed.
+    Storage,
+    /// A cryptographic operation or signature check failed.
+    Crypto,
+    /// The API was called with invalid arguments or in the wrong
+    /// state.
+    Usage,
+    /// Any other error, including errors thrown by callbacks.
+    Other,
+}
+
+/// The kind of capability in
+/// [`MlSrsError::MissingRequiredCapability`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum CapabilityKindFFI {
+    Extension,
+    Proposal,
+    Credential,
+}
+
+#[uniffi::export]
+impl MlSrsError {
+    /// Classify the error for retry logic.
+    pub fn category(&self) -> ErrorCategoryFFI {
+        match self {
+            Self::MlsError { category, .. } => *category,
+            Self::AnyError { .. }
+            | Self::UnexpectedCallbackError { .. }
+            | Self::CallbackError { .. } => ErrorCategoryFFI::Other,
+            Self::MlsCodecError { .. } => ErrorCategoryFFI::ProtocolViolation,
+            Self::IoError { .. } => ErrorCategoryFFI::Storage,
+            #[cfg(feature = "sqlite")]
+            Self::SqliteError { .. } => ErrorCategoryFFI::Storage,
+            Self::UnexpecteMessageFormat
+            | Self::UnexpectedMessageTypeDetailed { .. }
+            | Self::UnexpectedProposalSender
+            | Self::InvalidKeyPackage { .. }
+            | Self::CustomProposalNotAllowed { .. }
+            | Self::MissingRequiredCapability { .. }
+            | Self::KeyPackageExpired
+            | Self::DuplicateProposal { .. } => ErrorCategoryFFI::ProtocolViolation,
+            Self::WrongEpoch { .. } => ErrorCategoryFFI::StaleEpoch,
+            Self::InvalidSignature | Self::MalformedPublicKey => ErrorCategoryFFI::Crypto,
+            Self::InconsistentOptionalParameters
+            | Self::MissingBasicCredential
+            | Self::NotImplemented
+            | Self::Wiped
+            | Self::GroupSizeLimitExceeded { .. }
+            | Self::AuthenticatedDataTooLarge { .. }
+            | Self::SeedTooShort { .. } => ErrorCategoryFFI::Usage,
+        }
+    }
+
+    /// Whether the operation may succeed when retried.
+    ///
+    /// Storage failures may be transient, and messages from another
+    /// epoch may be processed once the group caught up. Other errors
+    /// will happen again for the same input.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self.category(),
+            ErrorCategoryFFI::StaleEpoch | ErrorCategoryFFI::Storage
+        )
+    }
 }
 
-impl IntoAnyError for MlSrsError {}
+fn mls_error_category(error: &mls_rs::error::MlsError) -> ErrorCategoryFFI {
+    use mls_rs::error::MlsError;
+
+    match error {
+        MlsError::GroupStorageError(_)
+        | MlsError::KeyPackageRepoError(_)
+        | MlsError::PskStoreError(_) => ErrorCategoryFFI::Storage,
+        MlsError::CryptoProviderError(_) => ErrorCategoryFFI::Crypto,
+        MlsError::IdentityProviderError(_) | MlsError::MlsRulesError(_) => ErrorCategoryFFI::Other,
+        MlsError::GroupNotFound
+        | MlsError::UnsupportedCipherSuite(_)
+        | MlsError::InvalidNodeIndex(_)
+        | MlsError::CantProcessMessageFromSelf => ErrorCategoryFFI::Usage,
+        _ => ErrorCategoryFFI::ProtocolViolation,
+    }
+}
+
+impl From<mls_rs::error::MlsError> for MlSrsError {
+    fn from(inner: mls_rs::error::MlsError) -> Self {
+        use mls_rs::error::MlsError;
+
+        if let Some(err) = callback_error(&inner) {
+            return err;
+        }
+
+        match inner {
+            MlsError::InvalidEpoch => Self::WrongEpoch {
+                message_epoch: None,
+                group_epoch: None,
+            },
+            MlsError::InvalidSignature => Self::InvalidSignature,
+            MlsError::InvalidLifetime => Self::KeyPackageExpired,
+            MlsError::MoreThanOneProposalForLeaf(leaf_index) => {
+                Self::DuplicateProposal { leaf_index }
+            }
+            MlsError::RequiredExtensionNotFound(extension_type) => {
+                Self::MissingRequiredCapability {
+                    kind: CapabilityKindFFI::Extension,
+                    raw_value: extension_type.raw_value(),
+                }
+            }
+            MlsError::RequiredProposalNotFound(proposal_type) => Self::MissingRequiredCapability {
+                kind: CapabilityKindFFI::Proposal,
+                raw_value: proposal_type.raw_value(),
+            },
+            MlsError::RequiredCredentialNotFound(credential_type) => {
+                Self::MissingRequiredCapability {
+                    kind: CapabilityKindFFI::Credential,
+                    raw_value: credential_type.raw_value(),
+                }
+            }
+            inner => Self::MlsError {
+                message: inner.to_string(),
+                category: mls_error_category(&inner),
+            },
+        }
+    }
+}
+
+impl From<mls_rs::error::AnyError> for MlSrsError {
+    fn from(inner: mls_rs::error::AnyError) -> Self {
+        if let Some(err) = callback_error(&inner) {
+            return err;
+        }
+
+        Self::AnyError {
+            message: inner.to_string(),
+        }
+    }
+}
+
+impl From<mls_rs_core::mls_rs_codec::Error> for MlSrsError {
+    fn from(inner: mls_rs_core::mls_rs_codec::Error) -> Self {
+        Self::MlsCodecError {
+            message: inner.to_string(),
+        }
+    }
+}
+
+impl From<uniffi::UnexpectedUniFFICallbackError> for MlSrsError {
+    fn from(inner: uniffi::UnexpectedUniFFICallbackError) -> Self {
+        Self::UnexpectedCallbackError {
+            message: inner.reason,
+        }
+    }
+}
+
+impl From<std::io::Error> for MlSrsError {
+    fn from(inner: std::io::Error) -> Self {
+        Self::IoError {
+            message: inner.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for MlSrsError {
+    fn from(inner: rusqlite::Error) -> Self {
+        Self::SqliteError {
+            message: inner.to_string(),
+        }
+    }
+}
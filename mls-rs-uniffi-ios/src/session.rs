@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[cfg(not(mls_build_async))]
+use std::sync::Mutex;
+#[cfg(mls_build_async)]
+use tokio::sync::Mutex;
+
+use crate::client::ClientFFI;
+use crate::group::{GroupFFI, JoinInfo};
+use crate::message::{MessageFFI, ReceivedMessageFFI};
+use crate::mls_rs_error::ErrorCategoryFFI;
+use crate::MlSrsError;
+
+/// A client together with the groups it has loaded.
+///
+/// Incoming messages are routed to their group by group id. Groups
+/// are loaded from storage the first time a message for them
+/// arrives and kept in memory afterwards.
+#[derive(uniffi::Object)]
+pub struct SessionFFI {
+    client: Arc<ClientFFI>,
+    groups: Mutex<HashMap<Vec<u8>, Arc<GroupFFI>>>,
+}
+
+/// A message processed by [`SessionFFI::handle_message`].
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct SessionMessageFFI {
+    /// The group which processed the message.
+    pub group_id: Vec<u8>,
+    pub message: ReceivedMessageFFI,
+}
+
+/// The outcome for one group of [`SessionFFI::propose_leave`].
+#[derive(Clone, Debug, uniffi::Enum)]
+pub enum LeaveProposalFFI {
+    /// The proposal to send to the group.
+    Proposed {
+        group_id: Vec<u8>,
+        message: Arc<MessageFFI>,
+    },
+    /// No proposal was created, the group is unchanged.
+    Failed {
+        group_id: Vec<u8>,
+        error: String,
+        category: ErrorCategoryFFI,
+    },
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+impl SessionFFI {
+    #[cfg(not(mls_build_async))]
+    fn groups(&self) -> std::sync::MutexGuard<'_, HashMap<Vec<u8>, Arc<GroupFFI>>> {
+        self.groups.lock().unwrap()
+    }
+
+    #[cfg(mls_build_async)]
+    async fn groups(&self) -> tokio::sync::MutexGuard<'_, HashMap<Vec<u8>, Arc<GroupFFI>>> {
+        self.groups.lock().await
+    }
+}
+
+#[cfg_attr(not(mls_build_async), maybe_async::must_be_sync)]
+#[cfg_attr(mls_build_async, maybe_async::must_be_async)]
+#[uniffi::export]
+impl SessionFFI {
+    #[uniffi::constructor]
+    pub fn new(client: Arc<ClientFFI>) -> Self {
+        Self {
+            client,
+            groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn client(&self) -> Arc<ClientFFI> {
+        self.client.clone()
+    }
+
+    /// The group with id `group_id`, loaded from storage if it is not
+    /// loaded yet.
+    pub async fn group(&self, group_id: Vec<u8>) -> Result<Arc<GroupFFI>, MlSrsError> {
+        let mut groups = self.groups().await;
+        if let Some(group) = groups.get(&group_id) {
+            return Ok(group.clone());
+        }
+        let group = Arc::new(self.client.load_group(group_id.clone()).await?);
+        groups.insert(group_id, group.clone());
+        Ok(group)
+    }
+
+    /// Add a group created outside of the session, e.g., with
+    /// [`ClientFFI::create_group`], replacing a loaded group with the
+    /// same id.
+    pub async fn insert_group(&self, group: Arc<GroupFFI>) -> Result<(), MlSrsError> {
+        let group_id = group.group_id().await?;
+        self.groups().await.insert(group_id, group);
+        Ok(())
+    }
+
+    /// Drop the group with id `group_id` from memory. It is loaded
+    /// from storage again when needed.
+    pub async fn unload_group(&self, group_id: Vec<u8>) {
+        self.groups().await.remove(&group_id);
+    }
+
+    /// Ids of the groups currently held in memory.
+    pub async fn loaded_group_ids(&self) -> Vec<Vec<u8>> {
+        self.groups().await.keys().cloned().collect()
+    }
+
+    /// Join a group with a Welcome message and add it to the session.
+    ///
+    /// See [`ClientFFI::join_group`] for details.
+    pub async fn join_group(&self, welcome_message: &MessageFFI) -> Result<JoinInfo, MlSrsError> {
+        let join_info = self.client.join_group(welcome_message).await?;
+        self.insert_group(join_info.group.clone()).await?;
+        Ok(join_info)
+    }
+
+    /// Process a serialized group message with the group it belongs
+    /// to.
+    ///
+    /// Messages without a group id, i.e., Welcome and KeyPackage
+    /// messages, are rejected with
+    /// [`MlSrsError::UnexpecteMessageFormat`].
+    pub async fn handle_message(&self, bytes: Vec<u8>) -> Result<SessionMessageFFI, MlSrsError> {
+        let message = mls_rs::MlsMessage::from_bytes(&bytes)?;
+        let group_id = message
+            .group_id()
+            .ok_or(MlSrsError::UnexpecteMessageFormat)?
+            .to_vec();
+        let group = self.group(group_id.clone()).await?;
+        let message = group.process_message(message).await?;
+        Ok(SessionMessageFFI { group_id, message })
+    }
+
+    /// Propose to leave each of the linked groups `group_ids`.
+    ///
+    /// The proposals are to be sent to the groups and committed by a
+    /// partner, see [`GroupFFI::propose_leave`]. A failure in one
+    /// group doesn't stop the others, so the result has an entry for
+    /// every group, in the order of `group_ids`. Proposals already
+    /// cached in other groups are kept, and must still be sent.
+    pub async fn propose_leave(
+        &self,
+        group_ids: Vec<Vec<u8>>,
+        authenticated_data: Vec<u8>,
+    ) -> Vec<LeaveProposalFFI> {
+        let mut proposals = Vec::with_capacity(group_ids.len());
+        for group_id in group_ids {
+            let message = match self.group(group_id.clone()).await {
+                Ok(group) => group.propose_leave(authenticated_data.clone()).await,
+                Err(error) => Err(error),
+            };
+            proposals.push(match message {
+                Ok(message) => LeaveProposalFFI::Proposed {
+                    group_id,
+                    message: Arc::new(message),
+                },
+                Err(error) => LeaveProposalFFI::Failed {
+                    group_id,
+                    error: error.to_string(),
+                    category: error.category(),
+                },
+            });
+        }
+        proposals
+    }
+}
@@ -0,0 +1,72 @@
+//! Reports the tracing spans of group operations as metrics.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::config::metrics::{MetricFFI, Metrics, MetricsProtocol};
+use crate::MlSrsError;
+
+/// Report the tracing spans of group operations to `metrics`.
+///
+/// This installs a global tracing subscriber with a
+/// [`SpanMetricsLayer`], typically with the same [`MetricsProtocol`]
+/// as in [`ClientConfigFFI::metrics`](crate::config::ClientConfigFFI::metrics).
+/// It fails if the process already has a global subscriber, e.g., when
+/// called twice. Rust applications with their own subscriber should
+/// add the layer to it instead.
+#[uniffi::export]
+pub fn enable_span_metrics(metrics: Arc<dyn MetricsProtocol>) -> Result<(), MlSrsError> {
+    let subscriber = tracing_subscriber::registry().with(SpanMetricsLayer::new(metrics));
+    tracing::subscriber::set_global_default(subscriber).map_err(|err| MlSrsError::AnyError {
+        message: err.to_string(),
+    })
+}
+
+/// A tracing [`Layer`] which reports each closed span, e.g., a commit
+/// or processing an incoming message, as a [`MetricFFI::Span`].
+///
+/// Installed by [`enable_span_metrics`], or added to the subscriber of
+/// a Rust application.
+#[derive(Clone, Debug)]
+pub struct SpanMetricsLayer {
+    metrics: Metrics,
+}
+
+impl SpanMetricsLayer {
+    pub fn new(metrics: Arc<dyn MetricsProtocol>) -> Self {
+        Self {
+            metrics: Metrics::new(Some(metrics)),
+        }
+    }
+}
+
+/// Stored in the span extensions when the span is created.
+struct SpanStart(Instant);
+
+impl<S> Layer<S> for SpanMetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStart(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        if let Some(SpanStart(start)) = span.extensions().get::<SpanStart>() {
+            self.metrics.report(MetricFFI::Span {
+                name: span.name().to_string(),
+                duration_micros: start.elapsed().as_micros() as u64,
+            });
+        }
+    }
+}
@@ -0,0 +1,22 @@
+#!/bin/sh
+# Build the library for Android and generate the Kotlin bindings.
+#
+# Requires the Android NDK (ANDROID_NDK_HOME) and cargo-ndk:
+#   cargo install cargo-ndk
+set -e
+
+rustup target add aarch64-linux-android armv7-linux-androideabi x86_64-linux-android
+
+# CryptoKit is Apple only, use the RustCrypto backend instead.
+FEATURES="--no-default-features --features rustcrypto"
+
+cargo ndk \
+    -t arm64-v8a -t armeabi-v7a -t x86_64 \
+    -o ./buildAndroid/jniLibs \
+    build --release $FEATURES
+
+# Generate the bindings from one of the Android libraries, a host
+# build would be a .dylib on macOS.
+cargo run -p uniffi-bindgen --bin uniffi-bindgen \
+    generate --library ./buildAndroid/jniLibs/arm64-v8a/libmls_rs_uniffi_ios.so --language kotlin \
+    --out-dir ./buildAndroid/kotlin
@@ -82,6 +82,15 @@ try ScriptTask(
 )
 .run()
 
+// The async build shares most code with the sync build, check that it
+// still compiles.
+try ScriptTask(
+    path: cargoPath,
+    arguments: ["check"],
+    env: ["RUSTFLAGS": "--cfg mls_build_async"]
+)
+.run()
+
 try ScriptTask(
     path: cargoPath,
     arguments: [